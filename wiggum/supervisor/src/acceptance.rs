@@ -0,0 +1,321 @@
+use crate::api_flow::FlowSpec;
+use crate::browser::Browser;
+use crate::llm::{Conversation, LlmClient};
+use crate::state::{Intent, Task};
+use crate::verdict::{Finding, Severity};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SuiteStatus {
+    /// Synthesised but not reviewed; runs, but may still be replaced
+    Draft,
+    /// Frozen: any later edit is reported instead of used
+    Approved,
+}
+
+/// Browser actions a script can take, run by the browser driver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BrowserAction {
+    Goto,
+    Click,
+    Fill,
+    Press,
+    WaitFor,
+    ExpectText,
+    ExpectVisible,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrowserStep {
+    pub action: BrowserAction,
+    /// CSS selector the action targets
+    #[serde(default)]
+    pub selector: Option<String>,
+    /// URL path for `goto`, text for `fill`/`expect_text`, key for `press`
+    #[serde(default)]
+    pub value: Option<String>,
+}
+
+/// A user journey through the client, e.g. add a todo and see it listed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrowserScript {
+    pub name: String,
+    pub steps: Vec<BrowserStep>,
+}
+
+/// One acceptance test: an API flow or a browser script checking a piece
+/// of the intent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AcceptanceTest {
+    pub id: String,
+    #[serde(default)]
+    pub task_id: Option<String>,
+    /// Ids of the intent criteria the test is evidence for
+    #[serde(default)]
+    pub criteria: Vec<String>,
+    pub description: String,
+    #[serde(default)]
+    pub api: Option<FlowSpec>,
+    #[serde(default)]
+    pub browser: Option<BrowserScript>,
+}
+
+/// Acceptance tests derived from the intent (state/acceptance.json).
+///
+/// Once approved (`ralph-wiggum-supervisor approve-tests`) the suite is
+/// fingerprinted; the gates run it on every pass and treat any change to
+/// it as tampering rather than as new tests.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AcceptanceSuite {
+    pub status: SuiteStatus,
+    #[serde(default)]
+    pub approved_at: Option<DateTime<Utc>>,
+    /// SHA-256 of `tests` when the suite was approved
+    #[serde(default)]
+    fingerprint: Option<String>,
+    pub tests: Vec<AcceptanceTest>,
+}
+
+/// Outcome of one acceptance test; `passed` is `None` when it could not run.
+#[derive(Debug, Clone)]
+pub struct AcceptanceResult {
+    pub test_id: String,
+    pub criteria: Vec<String>,
+    pub description: String,
+    pub passed: Option<bool>,
+    pub failures: Vec<String>,
+    /// Console errors and failed requests seen by the browser script
+    pub browser_findings: Vec<Finding>,
+}
+
+pub fn suite_path(state_dir: &Path) -> PathBuf {
+    state_dir.join("acceptance.json")
+}
+
+impl AcceptanceSuite {
+    pub fn load(state_dir: &Path) -> Result<Option<Self>> {
+        let path = suite_path(state_dir);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(path)?;
+        Ok(Some(serde_json::from_str(&content)?))
+    }
+
+    pub fn save(&self, state_dir: &Path) -> Result<()> {
+        fs::create_dir_all(state_dir)?;
+        fs::write(suite_path(state_dir), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Freeze the current tests.
+    pub fn approve(&mut self) -> Result<()> {
+        self.fingerprint = Some(fingerprint(&self.tests)?);
+        self.status = SuiteStatus::Approved;
+        self.approved_at = Some(Utc::now());
+        Ok(())
+    }
+
+    /// Whether the tests still match what was approved. Drafts always do.
+    pub fn is_intact(&self) -> Result<bool> {
+        match (&self.status, &self.fingerprint) {
+            (SuiteStatus::Draft, _) => Ok(true),
+            (SuiteStatus::Approved, Some(approved)) => Ok(*approved == fingerprint(&self.tests)?),
+            (SuiteStatus::Approved, None) => Ok(false),
+        }
+    }
+
+    /// Run every test against the app at `base_url`. Browser scripts run
+    /// in headless Chromium; without one they are reported as not run.
+    pub async fn run(&self, base_url: &str) -> Vec<AcceptanceResult> {
+        let http = Client::new();
+        let browser = if self.tests.iter().any(|t| t.browser.is_some()) {
+            Browser::launch()
+                .await
+                .map_err(|e| warn!("Browser acceptance tests will not run: {}", e))
+                .ok()
+        } else {
+            None
+        };
+
+        let mut results = Vec::new();
+        for test in &self.tests {
+            let mut result = AcceptanceResult {
+                test_id: test.id.clone(),
+                criteria: test.criteria.clone(),
+                description: test.description.clone(),
+                passed: None,
+                failures: Vec::new(),
+                browser_findings: Vec::new(),
+            };
+
+            if let Some(flow) = &test.api {
+                let flow_result = flow.run(&http, base_url).await;
+                result.passed = Some(flow_result.passed());
+                result.failures = flow_result.failures;
+            }
+
+            if let (Some(script), Some(browser)) = (&test.browser, &browser) {
+                match browser.run_script(base_url, script).await {
+                    Ok(run) => {
+                        result.passed = Some(result.passed.unwrap_or(true) && run.passed());
+                        result.browser_findings = run.findings();
+                        result.failures.extend(run.failures);
+                    }
+                    Err(e) => {
+                        result.passed = Some(false);
+                        result.failures.push(format!(
+                            "browser script \"{}\" could not run: {}",
+                            script.name, e
+                        ));
+                    }
+                }
+            }
+
+            results.push(result);
+        }
+
+        results
+    }
+
+    /// Findings for failed tests. Failures of an approved suite are
+    /// critical: they are the agreed definition of done.
+    pub fn findings(&self, results: &[AcceptanceResult]) -> Result<Vec<Finding>> {
+        let severity = match self.status {
+            SuiteStatus::Approved => Severity::Critical,
+            SuiteStatus::Draft => Severity::Major,
+        };
+
+        let mut findings = Vec::new();
+        if !self.is_intact()? {
+            findings.push(Finding::new(
+                Severity::Critical,
+                "Approved acceptance tests were modified; restore state/acceptance.json or re-approve the suite",
+            ).rule("acceptance:tampered"));
+        }
+
+        findings.extend(
+            results
+                .iter()
+                .flat_map(|r| r.browser_findings.iter().cloned()),
+        );
+        for result in results.iter().filter(|r| r.passed == Some(false)) {
+            for failure in &result.failures {
+                findings.push(
+                    Finding::new(
+                        severity,
+                        format!(
+                            "Acceptance test \"{}\" failed: {}",
+                            result.description, failure
+                        ),
+                    )
+                    .rule(format!("acceptance:{}", result.test_id)),
+                );
+            }
+        }
+
+        Ok(findings)
+    }
+}
+
+impl AcceptanceResult {
+    pub fn summary(&self) -> String {
+        let status = match self.passed {
+            Some(true) => "passed",
+            Some(false) => "FAILED",
+            None => "not run",
+        };
+        format!(
+            "acceptance {} \"{}\": {}",
+            self.test_id, self.description, status
+        )
+    }
+}
+
+fn fingerprint(tests: &[AcceptanceTest]) -> Result<String> {
+    let digest = Sha256::digest(serde_json::to_vec(tests)?);
+    Ok(digest.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+const SYNTHESIS_PROMPT: &str = "You write acceptance tests: concrete, runnable checks that a web app does what the user asked for.
+Each test checks one observable behaviour from the user's point of view. Prefer checking data the user created round-trips over checking status codes alone.
+
+Respond with a single JSON object and nothing else:
+{ \"tests\": [
+  { \"id\": \"AT-1\", \"task_id\": \"task id or null\", \"criteria\": [\"AC-1\"], \"description\": \"what the user can do\",
+    \"api\": { \"name\": \"...\", \"variables\": {}, \"steps\": [
+      { \"name\": \"...\", \"request\": { \"method\": \"POST\", \"path\": \"/api/...\", \"json\": {} },
+        \"expect\": { \"status\": 201, \"json\": [ { \"path\": \"$.field\", \"equals\": \"...\" } ] },
+        \"capture\": { \"variable\": \"$.id\" } } ] },
+    \"browser\": { \"name\": \"...\", \"steps\": [
+      { \"action\": \"goto\" | \"click\" | \"fill\" | \"press\" | \"wait_for\" | \"expect_text\" | \"expect_visible\", \"selector\": \"css or null\", \"value\": \"text, path or key\" } ] } } ] }
+Give each test an api flow, a browser script, or both, and list the acceptance criteria it checks. Every criterion needs at least one test. Use {{variable}} to reuse captured values.
+Only use routes and pages that exist in the repository map.";
+
+#[derive(Deserialize)]
+struct SynthesisedTests {
+    tests: Vec<AcceptanceTest>,
+}
+
+/// Ask the model to turn the intent and its tasks into a draft suite.
+pub async fn synthesize(
+    llm: &LlmClient,
+    intent: &Intent,
+    tasks: &[Task],
+    repo_map: &str,
+) -> Result<AcceptanceSuite> {
+    let task_list = tasks
+        .iter()
+        .map(|t| {
+            if t.criteria.is_empty() {
+                format!("- {}: {}", t.id, t.description)
+            } else {
+                format!("- {} ({}): {}", t.id, t.criteria.join(", "), t.description)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut conversation = Conversation::new(SYNTHESIS_PROMPT);
+    conversation.push_user(&format!(
+        "USER INTENT:\n{}\n\nTASKS:\n{}\n\nREPOSITORY MAP:\n{}",
+        intent.render(),
+        task_list,
+        repo_map
+    ));
+
+    let reply = llm.converse(&mut conversation, &[], llm.model()).await?;
+    let reply = reply.content.unwrap_or_default();
+    let start = reply
+        .find('{')
+        .ok_or_else(|| anyhow!("no JSON object in acceptance test reply"))?;
+    let end = reply
+        .rfind('}')
+        .filter(|&end| end > start)
+        .ok_or_else(|| anyhow!("no JSON object in acceptance test reply"))?;
+    let synthesised: SynthesisedTests = serde_json::from_str(&reply[start..=end])?;
+
+    if synthesised.tests.is_empty() {
+        return Err(anyhow!("model proposed no acceptance tests"));
+    }
+
+    info!(
+        "Synthesised {} acceptance tests from the intent",
+        synthesised.tests.len()
+    );
+    Ok(AcceptanceSuite {
+        status: SuiteStatus::Draft,
+        approved_at: None,
+        fingerprint: None,
+        tests: synthesised.tests,
+    })
+}
@@ -0,0 +1,536 @@
+use crate::llm::{Conversation, LlmClient};
+use crate::verdict::{Finding, Severity};
+use anyhow::{anyhow, Result};
+use reqwest::{Client, Method};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{info, warn};
+
+const STEP_TIMEOUT: Duration = Duration::from_secs(30);
+/// Response body shown with a failed assertion.
+const MAX_BODY_EXCERPT: usize = 500;
+
+/// A multi-step HTTP scenario (state/flows/*.json, *.yaml or *.yml).
+///
+/// ```yaml
+/// name: create and list todos
+/// variables: { title: Buy milk }
+/// steps:
+///   - name: create
+///     request: { method: POST, path: /api/todos, json: { title: "{{title}}" } }
+///     expect:
+///       status: 201
+///       headers: { content-type: application/json }
+///       json:
+///         - { path: $.title, equals: "{{title}}" }
+///     capture: { todo_id: $.id }
+///   - name: fetch
+///     request: { method: GET, path: "/api/todos/{{todo_id}}" }
+///     expect: { status: 200 }
+/// ```
+///
+/// `{{name}}` is replaced in paths, headers and bodies with a variable or
+/// an earlier capture.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlowSpec {
+    pub name: String,
+    /// Defaults to the app under test
+    #[serde(default)]
+    pub base_url: Option<String>,
+    #[serde(default)]
+    pub variables: BTreeMap<String, Value>,
+    pub steps: Vec<FlowStep>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlowStep {
+    pub name: String,
+    pub request: FlowRequest,
+    #[serde(default)]
+    pub expect: Expectations,
+    /// Variable name -> JSON path into the response body
+    #[serde(default)]
+    pub capture: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlowRequest {
+    #[serde(default = "default_method")]
+    pub method: String,
+    pub path: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub json: Option<Value>,
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Expectations {
+    #[serde(default)]
+    pub status: Option<StatusExpectation>,
+    /// Header name -> text its value must contain
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub json: Vec<JsonAssertion>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum StatusExpectation {
+    Exact(u16),
+    AnyOf(Vec<u16>),
+}
+
+impl StatusExpectation {
+    fn matches(&self, status: u16) -> bool {
+        match self {
+            StatusExpectation::Exact(expected) => status == *expected,
+            StatusExpectation::AnyOf(expected) => expected.contains(&status),
+        }
+    }
+}
+
+impl std::fmt::Display for StatusExpectation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StatusExpectation::Exact(expected) => write!(f, "{}", expected),
+            StatusExpectation::AnyOf(expected) => {
+                let codes: Vec<String> = expected.iter().map(|c| c.to_string()).collect();
+                write!(f, "one of {}", codes.join(", "))
+            }
+        }
+    }
+}
+
+/// Check on one value in the response body; every given condition must hold.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonAssertion {
+    pub path: String,
+    #[serde(default)]
+    pub equals: Option<Value>,
+    #[serde(default)]
+    pub exists: Option<bool>,
+    /// Text the value (as a string) must contain
+    #[serde(default)]
+    pub contains: Option<String>,
+    /// Length of an array, object or string
+    #[serde(default)]
+    pub length: Option<usize>,
+}
+
+/// Outcome of running one flow; it stops at the first failing step.
+#[derive(Debug, Clone)]
+pub struct FlowResult {
+    pub name: String,
+    pub source: Option<PathBuf>,
+    pub steps_passed: usize,
+    pub steps_total: usize,
+    pub failures: Vec<String>,
+}
+
+impl FlowResult {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    pub fn summary(&self) -> String {
+        let status = if self.passed() { "passed" } else { "FAILED" };
+        format!(
+            "flow \"{}\": {} ({}/{} steps)",
+            self.name, status, self.steps_passed, self.steps_total
+        )
+    }
+
+    pub fn findings(&self) -> Vec<Finding> {
+        self.failures
+            .iter()
+            .map(|failure| {
+                let finding = Finding::new(
+                    Severity::Major,
+                    format!("API flow \"{}\": {}", self.name, failure),
+                )
+                .rule(format!("flow:{}", self.name));
+                match &self.source {
+                    Some(source) => finding.at(source.display().to_string(), None),
+                    None => finding,
+                }
+            })
+            .collect()
+    }
+}
+
+pub fn flows_dir(state_dir: &Path) -> PathBuf {
+    state_dir.join("flows")
+}
+
+/// Every flow spec in `dir`, with the file it came from.
+pub fn load_flows(dir: &Path) -> Result<Vec<(PathBuf, FlowSpec)>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| {
+            matches!(
+                p.extension().and_then(|e| e.to_str()),
+                Some("json" | "yaml" | "yml")
+            )
+        })
+        .collect();
+    paths.sort();
+
+    let mut flows = Vec::new();
+    for path in paths {
+        match FlowSpec::load(&path) {
+            Ok(spec) => flows.push((path, spec)),
+            Err(e) => warn!("Skipping flow spec {}: {}", path.display(), e),
+        }
+    }
+    Ok(flows)
+}
+
+impl FlowSpec {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        if path.extension().and_then(|e| e.to_str()) == Some("json") {
+            Ok(serde_json::from_str(&content)?)
+        } else {
+            Ok(serde_yaml::from_str(&content)?)
+        }
+    }
+
+    /// Run the steps in order against `base_url` (unless the spec names its own).
+    pub async fn run(&self, http: &Client, base_url: &str) -> FlowResult {
+        let base_url = self
+            .base_url
+            .as_deref()
+            .unwrap_or(base_url)
+            .trim_end_matches('/');
+        let mut variables = self.variables.clone();
+        let mut result = FlowResult {
+            name: self.name.clone(),
+            source: None,
+            steps_passed: 0,
+            steps_total: self.steps.len(),
+            failures: Vec::new(),
+        };
+
+        for step in &self.steps {
+            match step.run(http, base_url, &mut variables).await {
+                Ok(failures) if failures.is_empty() => result.steps_passed += 1,
+                Ok(failures) => {
+                    result.failures.extend(
+                        failures
+                            .into_iter()
+                            .map(|f| format!("step \"{}\": {}", step.name, f)),
+                    );
+                    break;
+                }
+                Err(e) => {
+                    result
+                        .failures
+                        .push(format!("step \"{}\": request failed: {}", step.name, e));
+                    break;
+                }
+            }
+        }
+
+        info!("{}", result.summary());
+        result
+    }
+}
+
+impl FlowStep {
+    /// Send the request and check it; returns the failed expectations.
+    async fn run(
+        &self,
+        http: &Client,
+        base_url: &str,
+        variables: &mut BTreeMap<String, Value>,
+    ) -> Result<Vec<String>> {
+        let method = Method::from_bytes(self.request.method.to_uppercase().as_bytes())?;
+        let url = format!(
+            "{}{}",
+            base_url,
+            interpolate_str(&self.request.path, variables)
+        );
+
+        let mut request = http.request(method, &url).timeout(STEP_TIMEOUT);
+        for (name, value) in &self.request.headers {
+            request = request.header(name, interpolate_str(value, variables));
+        }
+        if let Some(body) = &self.request.json {
+            request = request.json(&interpolate(body, variables));
+        }
+
+        let response = request.send().await?;
+        let status = response.status().as_u16();
+        let headers = response.headers().clone();
+        let text = response.text().await.unwrap_or_default();
+        let body: Option<Value> = serde_json::from_str(&text).ok();
+
+        let mut failures = Vec::new();
+        let excerpt = || text.chars().take(MAX_BODY_EXCERPT).collect::<String>();
+
+        if let Some(expected) = &self.expect.status {
+            if !expected.matches(status) {
+                failures.push(format!(
+                    "expected status {}, got {} (body: {})",
+                    expected,
+                    status,
+                    excerpt()
+                ));
+            }
+        }
+
+        for (name, wanted) in &self.expect.headers {
+            let wanted = interpolate_str(wanted, variables);
+            match headers.get(name).and_then(|v| v.to_str().ok()) {
+                Some(value) if value.contains(&wanted) => {}
+                Some(value) => failures.push(format!(
+                    "header {} is {:?}, expected it to contain {:?}",
+                    name, value, wanted
+                )),
+                None => failures.push(format!("header {} missing", name)),
+            }
+        }
+
+        for assertion in &self.expect.json {
+            if let Err(failure) = assertion.check(body.as_ref(), variables) {
+                failures.push(format!("{} (body: {})", failure, excerpt()));
+            }
+        }
+
+        for (name, path) in &self.capture {
+            match body.as_ref().and_then(|b| select(b, path)) {
+                Some(value) => {
+                    variables.insert(name.clone(), value.clone());
+                }
+                None => failures.push(format!("could not capture {} from {}", name, path)),
+            }
+        }
+
+        Ok(failures)
+    }
+}
+
+impl JsonAssertion {
+    fn check(
+        &self,
+        body: Option<&Value>,
+        variables: &BTreeMap<String, Value>,
+    ) -> Result<(), String> {
+        let body = body.ok_or_else(|| format!("{}: response is not JSON", self.path))?;
+        let value = select(body, &self.path);
+
+        if let Some(exists) = self.exists {
+            if value.is_some() != exists {
+                return Err(format!(
+                    "{} {}",
+                    self.path,
+                    if exists {
+                        "is missing"
+                    } else {
+                        "should not exist"
+                    }
+                ));
+            }
+        }
+
+        let Some(value) = value else {
+            if self.equals.is_some() || self.contains.is_some() || self.length.is_some() {
+                return Err(format!("{} is missing", self.path));
+            }
+            return Ok(());
+        };
+
+        if let Some(expected) = &self.equals {
+            let expected = interpolate(expected, variables);
+            if *value != expected {
+                return Err(format!("{} is {}, expected {}", self.path, value, expected));
+            }
+        }
+
+        if let Some(needle) = &self.contains {
+            let needle = interpolate_str(needle, variables);
+            let haystack = value
+                .as_str()
+                .map(str::to_string)
+                .unwrap_or_else(|| value.to_string());
+            if !haystack.contains(&needle) {
+                return Err(format!(
+                    "{} is {}, expected it to contain {:?}",
+                    self.path, value, needle
+                ));
+            }
+        }
+
+        if let Some(expected) = self.length {
+            let length = match value {
+                Value::Array(items) => items.len(),
+                Value::Object(fields) => fields.len(),
+                Value::String(text) => text.chars().count(),
+                _ => return Err(format!("{} has no length", self.path)),
+            };
+            if length != expected {
+                return Err(format!(
+                    "{} has length {}, expected {}",
+                    self.path, length, expected
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Resolve a JSONPath subset: `$`, `.field`, `[index]` and `["field"]`.
+pub fn select<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    let mut current = value;
+    let mut rest = path.trim().strip_prefix('$').unwrap_or(path.trim());
+
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('.') {
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            current = current.get(&after[..end])?;
+            rest = &after[end..];
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let end = after.find(']')?;
+            let key = after[..end].trim();
+            current = match key.parse::<usize>() {
+                Ok(index) => current.get(index)?,
+                Err(_) => current.get(key.trim_matches(|c| c == '"' || c == '\''))?,
+            };
+            rest = &after[end + 1..];
+        } else {
+            // A bare leading field, e.g. `items[0]`
+            let end = rest.find(['.', '[']).unwrap_or(rest.len());
+            current = current.get(&rest[..end])?;
+            rest = &rest[end..];
+        }
+    }
+
+    Some(current)
+}
+
+/// Substitute `{{name}}` in every string of `value`. A string that is just
+/// one placeholder takes the variable's JSON value, so ids stay numbers.
+fn interpolate(value: &Value, variables: &BTreeMap<String, Value>) -> Value {
+    match value {
+        Value::String(text) => {
+            let trimmed = text.trim();
+            if let Some(name) = trimmed
+                .strip_prefix("{{")
+                .and_then(|t| t.strip_suffix("}}"))
+            {
+                if let Some(found) = variables.get(name.trim()) {
+                    return found.clone();
+                }
+            }
+            Value::String(interpolate_str(text, variables))
+        }
+        Value::Array(items) => {
+            Value::Array(items.iter().map(|v| interpolate(v, variables)).collect())
+        }
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(k, v)| (k.clone(), interpolate(v, variables)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+fn interpolate_str(text: &str, variables: &BTreeMap<String, Value>) -> String {
+    let mut out = text.to_string();
+    for (name, value) in variables {
+        let replacement = value
+            .as_str()
+            .map(str::to_string)
+            .unwrap_or_else(|| value.to_string());
+        out = out.replace(&format!("{{{{{}}}}}", name), &replacement);
+    }
+    out
+}
+
+/// Run every flow in `dir` against `base_url`.
+pub async fn run_flows(dir: &Path, base_url: &str) -> Result<Vec<FlowResult>> {
+    let http = Client::builder().build().map_err(|e| anyhow!(e))?;
+    let mut results = Vec::new();
+
+    for (path, spec) in load_flows(dir)? {
+        let mut result = spec.run(&http, base_url).await;
+        result.source = Some(path);
+        results.push(result);
+    }
+
+    Ok(results)
+}
+
+const FLOW_GENERATION_PROMPT: &str = "You write API flow specs: multi-step HTTP scenarios that check a web app's backend does what the user asked for.
+
+Respond with a single JSON object and nothing else:
+{ \"flows\": [ { \"name\": \"...\", \"variables\": {}, \"steps\": [
+  { \"name\": \"...\",
+    \"request\": { \"method\": \"POST\", \"path\": \"/api/...\", \"headers\": {}, \"json\": {} },
+    \"expect\": { \"status\": 201, \"headers\": {}, \"json\": [ { \"path\": \"$.field\", \"equals\": \"...\", \"exists\": true, \"contains\": \"...\", \"length\": 1 } ] },
+    \"capture\": { \"variable\": \"$.id\" } } ] } ] }
+Omit fields you do not need. Use {{variable}} to reuse captured values in later paths and bodies.
+Only use routes that exist in the repository map. Cover the core behaviour of the intent with 1-4 flows.";
+
+#[derive(Deserialize)]
+struct GeneratedFlows {
+    flows: Vec<FlowSpec>,
+}
+
+/// Ask the model for flow specs covering `intent`, given the app's routes
+/// (a repo map), and save them to `dir` as `generated-<n>.json` so they can
+/// be reviewed and edited like hand-written ones.
+pub async fn generate_flows(
+    llm: &LlmClient,
+    dir: &Path,
+    intent: &str,
+    repo_map: &str,
+) -> Result<Vec<FlowSpec>> {
+    let mut conversation = Conversation::new(FLOW_GENERATION_PROMPT);
+    conversation.push_user(&format!(
+        "USER INTENT:\n{}\n\nREPOSITORY MAP:\n{}",
+        intent, repo_map
+    ));
+
+    let reply = llm.converse(&mut conversation, &[], llm.model()).await?;
+    let reply = reply.content.unwrap_or_default();
+    let start = reply
+        .find('{')
+        .ok_or_else(|| anyhow!("no JSON object in flow generation reply"))?;
+    let end = reply
+        .rfind('}')
+        .filter(|&end| end > start)
+        .ok_or_else(|| anyhow!("no JSON object in flow generation reply"))?;
+    let generated: GeneratedFlows = serde_json::from_str(&reply[start..=end])?;
+
+    fs::create_dir_all(dir)?;
+    for (index, flow) in generated.flows.iter().enumerate() {
+        fs::write(
+            dir.join(format!("generated-{}.json", index + 1)),
+            serde_json::to_string_pretty(flow)?,
+        )?;
+    }
+
+    info!(
+        "Generated {} API flows from the intent",
+        generated.flows.len()
+    );
+    Ok(generated.flows)
+}
@@ -0,0 +1,190 @@
+use anyhow::{anyhow, Result};
+use reqwest::Client;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::{Child, Command};
+use tracing::{debug, info, warn};
+
+/// Where the generated server listens (the generate prompt asks for `npm start`).
+pub const DEFAULT_APP_URL: &str = "http://localhost:3000";
+pub const DEFAULT_APP_PORT: u16 = 3000;
+
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+/// Log lines kept in memory; older ones are dropped.
+const MAX_LOG_LINES: usize = 5000;
+
+/// One line the app wrote, tagged with the stream it came from.
+#[derive(Debug, Clone)]
+pub struct LogLine {
+    pub stderr: bool,
+    pub text: String,
+}
+
+/// The generated app's server, started with `npm start` for a gate to
+/// test against and stopped (with its whole process group) on drop.
+/// Reuses an app that is already listening instead of starting another.
+pub struct RunningApp {
+    child: Option<Child>,
+    base_url: String,
+    logs: Arc<Mutex<VecDeque<LogLine>>>,
+}
+
+impl RunningApp {
+    /// Start the server under `workspace` (its `server/` package, or the
+    /// workspace itself) and wait until it answers HTTP.
+    pub async fn start(workspace: &Path) -> Result<Self> {
+        let logs = Arc::new(Mutex::new(VecDeque::new()));
+        let base_url = DEFAULT_APP_URL.to_string();
+        let http = Client::new();
+
+        if responds(&http, &base_url).await {
+            info!("App already running at {}, reusing it", base_url);
+            return Ok(Self {
+                child: None,
+                base_url,
+                logs,
+            });
+        }
+
+        let dir = server_dir(workspace)
+            .ok_or_else(|| anyhow!("No server package.json in {}", workspace.display()))?;
+        info!("Starting app with npm start in {}", dir.display());
+
+        let mut command = Command::new("npm");
+        command
+            .arg("start")
+            .current_dir(&dir)
+            .env("PORT", DEFAULT_APP_PORT.to_string())
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        // Own process group, so stopping npm also stops the node it spawned
+        #[cfg(unix)]
+        command.process_group(0);
+
+        let mut child = command.spawn()?;
+        if let Some(stdout) = child.stdout.take() {
+            tokio::spawn(collect_lines(stdout, false, logs.clone()));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            tokio::spawn(collect_lines(stderr, true, logs.clone()));
+        }
+
+        let mut app = Self {
+            child: Some(child),
+            base_url,
+            logs,
+        };
+        let deadline = Instant::now() + STARTUP_TIMEOUT;
+
+        loop {
+            if responds(&http, &app.base_url).await {
+                info!("App is up at {}", app.base_url);
+                return Ok(app);
+            }
+
+            let exited = app.child.as_mut().and_then(|c| c.try_wait().ok().flatten());
+            if let Some(status) = exited {
+                return Err(anyhow!(
+                    "npm start exited with {} before serving:\n{}",
+                    status,
+                    app.log_tail(20)
+                ));
+            }
+            if Instant::now() >= deadline {
+                return Err(anyhow!(
+                    "App did not answer on {} within {}s:\n{}",
+                    app.base_url,
+                    STARTUP_TIMEOUT.as_secs(),
+                    app.log_tail(20)
+                ));
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Everything the app has logged so far (nothing if it was reused).
+    pub fn logs(&self) -> Vec<LogLine> {
+        self.logs.lock().unwrap().iter().cloned().collect()
+    }
+
+    pub fn log_tail(&self, lines: usize) -> String {
+        let logs = self.logs.lock().unwrap();
+        logs.iter()
+            .skip(logs.len().saturating_sub(lines))
+            .map(|l| l.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl Drop for RunningApp {
+    fn drop(&mut self) {
+        let Some(child) = self.child.as_mut() else {
+            return;
+        };
+
+        #[cfg(unix)]
+        if let Some(pid) = child.id() {
+            let _ = std::process::Command::new("kill")
+                .arg("-TERM")
+                .arg(format!("-{}", pid))
+                .status();
+        }
+        let _ = child.start_kill();
+        debug!("Stopped app at {}", self.base_url);
+    }
+}
+
+/// Whether the workspace has a server `RunningApp::start` knows how to run.
+pub fn has_server(workspace: &Path) -> bool {
+    server_dir(workspace).is_some()
+}
+
+fn server_dir(workspace: &Path) -> Option<PathBuf> {
+    [workspace.join("server"), workspace.to_path_buf()]
+        .into_iter()
+        .find(|dir| dir.join("package.json").exists())
+}
+
+/// Any HTTP response counts: a 404 on `/` still means the server is up.
+async fn responds(http: &Client, url: &str) -> bool {
+    http.get(url)
+        .timeout(Duration::from_secs(2))
+        .send()
+        .await
+        .is_ok()
+}
+
+async fn collect_lines(
+    stream: impl AsyncRead + Unpin,
+    stderr: bool,
+    logs: Arc<Mutex<VecDeque<LogLine>>>,
+) {
+    let mut lines = BufReader::new(stream).lines();
+    loop {
+        match lines.next_line().await {
+            Ok(Some(text)) => {
+                let mut logs = logs.lock().unwrap();
+                if logs.len() >= MAX_LOG_LINES {
+                    logs.pop_front();
+                }
+                logs.push_back(LogLine { stderr, text });
+            }
+            Ok(None) => break,
+            Err(e) => {
+                warn!("Stopped reading app output: {}", e);
+                break;
+            }
+        }
+    }
+}
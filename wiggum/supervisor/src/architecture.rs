@@ -0,0 +1,486 @@
+use crate::module_graph::ModuleGraph;
+use crate::verdict::{Finding, Severity};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Dependencies one module may have before it is flagged as a hub.
+pub const MAX_FAN_OUT: usize = 15;
+
+/// How much less stable than a module its dependency may be.
+const INSTABILITY_MARGIN: f64 = 0.5;
+
+/// Directories holding code for the browser, and for the server; neither
+/// side may import the other's files.
+const CLIENT_DIRS: [&str; 3] = ["client", "frontend", "web"];
+const SERVER_DIRS: [&str; 2] = ["server", "backend"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum GraphFormat {
+    Dot,
+    Json,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModuleMetrics {
+    pub path: String,
+    /// Modules that depend on this one
+    pub fan_in: usize,
+    /// Modules this one depends on
+    pub fan_out: usize,
+    /// fan_out / (fan_in + fan_out): 0 is depended upon only, 1 depends only
+    pub instability: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+    /// Line of the first import making the dependency
+    pub line: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ViolationKind {
+    /// An import into another package, or between client and server code
+    Boundary,
+    /// A module depending on one much less stable than itself
+    UnstableDependency,
+    FanOut,
+}
+
+impl ViolationKind {
+    fn severity(self) -> Severity {
+        match self {
+            ViolationKind::Boundary => Severity::Major,
+            ViolationKind::UnstableDependency | ViolationKind::FanOut => Severity::Minor,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ViolationKind::Boundary => "boundary",
+            ViolationKind::UnstableDependency => "unstable-dependency",
+            ViolationKind::FanOut => "fan-out",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Violation {
+    pub kind: ViolationKind,
+    pub module: String,
+    pub line: Option<u32>,
+    pub message: String,
+}
+
+/// The workspace's module dependency graph with its cycles, per-module
+/// coupling metrics and boundary violations. Rust `mod` items give the
+/// module tree, not dependencies, and are left out.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchitectureReport {
+    pub generated_at: DateTime<Utc>,
+    pub modules: Vec<ModuleMetrics>,
+    pub edges: Vec<Edge>,
+    /// Each cycle as the modules along it, starting from its first module
+    pub cycles: Vec<Vec<String>>,
+    pub violations: Vec<Violation>,
+}
+
+fn report_path(state_dir: &Path) -> PathBuf {
+    state_dir.join("architecture.json")
+}
+
+fn dot_path(state_dir: &Path) -> PathBuf {
+    state_dir.join("architecture.dot")
+}
+
+impl ArchitectureReport {
+    pub fn analyze(graph: &ModuleGraph) -> Self {
+        let mut edges: BTreeMap<(String, String), u32> = BTreeMap::new();
+        for module in graph.modules.values() {
+            for import in module.imports.iter().filter(|i| !i.declaration) {
+                let Some(target) = import
+                    .target
+                    .as_ref()
+                    .filter(|t| graph.modules.contains_key(*t) && **t != module.path)
+                else {
+                    continue;
+                };
+                edges
+                    .entry((module.path.clone(), target.clone()))
+                    .or_insert(import.line);
+            }
+        }
+        let edges: Vec<Edge> = edges
+            .into_iter()
+            .map(|((from, to), line)| Edge { from, to, line })
+            .collect();
+
+        let mut fan_in: HashMap<&str, usize> = HashMap::new();
+        let mut fan_out: HashMap<&str, usize> = HashMap::new();
+        for edge in &edges {
+            *fan_out.entry(edge.from.as_str()).or_default() += 1;
+            *fan_in.entry(edge.to.as_str()).or_default() += 1;
+        }
+        let modules: Vec<ModuleMetrics> = graph
+            .modules
+            .keys()
+            .map(|path| {
+                let fan_in = fan_in.get(path.as_str()).copied().unwrap_or(0);
+                let fan_out = fan_out.get(path.as_str()).copied().unwrap_or(0);
+                let instability = if fan_in + fan_out == 0 {
+                    0.0
+                } else {
+                    fan_out as f64 / (fan_in + fan_out) as f64
+                };
+                ModuleMetrics {
+                    path: path.clone(),
+                    fan_in,
+                    fan_out,
+                    instability,
+                }
+            })
+            .collect();
+
+        let cycles = cycles(&edges);
+        let violations = violations(graph, &modules, &edges, &cycles);
+
+        Self {
+            generated_at: Utc::now(),
+            modules,
+            edges,
+            cycles,
+            violations,
+        }
+    }
+
+    pub fn load(state_dir: &Path) -> Result<Option<Self>> {
+        let path = report_path(state_dir);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(path)?;
+        Ok(Some(serde_json::from_str(&content)?))
+    }
+
+    /// Write the report as JSON, and the graph as DOT next to it.
+    pub fn save(&self, state_dir: &Path) -> Result<()> {
+        fs::create_dir_all(state_dir)?;
+        fs::write(report_path(state_dir), self.to_json()?)?;
+        fs::write(dot_path(state_dir), self.to_dot())?;
+        Ok(())
+    }
+
+    pub fn render(&self, format: GraphFormat) -> Result<String> {
+        match format {
+            GraphFormat::Dot => Ok(self.to_dot()),
+            GraphFormat::Json => self.to_json(),
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Graphviz source: modules labelled with their instability, cycles
+    /// in red.
+    pub fn to_dot(&self) -> String {
+        let in_cycle: BTreeSet<&str> = self.cycles.iter().flatten().map(String::as_str).collect();
+        let cycle_edges: BTreeSet<(&str, &str)> = self
+            .cycles
+            .iter()
+            .flat_map(|cycle| {
+                cycle
+                    .iter()
+                    .zip(cycle.iter().cycle().skip(1))
+                    .map(|(a, b)| (a.as_str(), b.as_str()))
+            })
+            .collect();
+
+        let mut dot = String::from(
+            "digraph modules {\n    rankdir=LR;\n    node [shape=box, fontname=\"monospace\"];\n",
+        );
+        for module in &self.modules {
+            let color = if in_cycle.contains(module.path.as_str()) {
+                ", color=red"
+            } else {
+                ""
+            };
+            dot.push_str(&format!(
+                "    \"{}\" [label=\"{}\\nin {} out {} I={:.2}\"{}];\n",
+                module.path, module.path, module.fan_in, module.fan_out, module.instability, color
+            ));
+        }
+        for edge in &self.edges {
+            let color = if cycle_edges.contains(&(edge.from.as_str(), edge.to.as_str())) {
+                " [color=red]"
+            } else {
+                ""
+            };
+            dot.push_str(&format!(
+                "    \"{}\" -> \"{}\"{};\n",
+                edge.from, edge.to, color
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "{} modules, {} dependencies, {} cycles, {} violations",
+            self.modules.len(),
+            self.edges.len(),
+            self.cycles.len(),
+            self.violations.len()
+        );
+        if let Some(hub) = self
+            .modules
+            .iter()
+            .filter(|m| m.fan_in > 0)
+            .max_by_key(|m| m.fan_in)
+        {
+            summary.push_str(&format!(
+                "; most depended on: {} (fan-in {})",
+                hub.path, hub.fan_in
+            ));
+        }
+        summary
+    }
+
+    pub fn findings(&self) -> Vec<Finding> {
+        let mut findings: Vec<Finding> = self
+            .cycles
+            .iter()
+            .map(|cycle| {
+                let path = cycle
+                    .iter()
+                    .chain(cycle.first())
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(" -> ");
+                let line = self
+                    .edges
+                    .iter()
+                    .find(|e| e.from == cycle[0] && Some(&e.to) == cycle.get(1))
+                    .map(|e| e.line);
+                Finding::new(
+                    Severity::Major,
+                    format!(
+                        "Import cycle: {}; break it by moving the shared code into its own module",
+                        path
+                    ),
+                )
+                .at(cycle[0].clone(), line)
+                .rule("arch:cycle")
+            })
+            .collect();
+
+        findings.extend(self.violations.iter().map(|v| {
+            Finding::new(v.kind.severity(), v.message.clone())
+                .at(v.module.clone(), v.line)
+                .rule(format!("arch:{}", v.kind.name()))
+        }));
+        findings
+    }
+}
+
+/// One cycle per strongly connected component of more than one module,
+/// the shortest through the component's first module.
+fn cycles(edges: &[Edge]) -> Vec<Vec<String>> {
+    let mut adjacency: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for edge in edges {
+        adjacency
+            .entry(edge.from.as_str())
+            .or_default()
+            .push(edge.to.as_str());
+        adjacency.entry(edge.to.as_str()).or_default();
+    }
+
+    let mut cycles = Vec::new();
+    for component in strongly_connected(&adjacency)
+        .into_iter()
+        .filter(|c| c.len() > 1)
+    {
+        let members: BTreeSet<&str> = component.iter().copied().collect();
+        let start = *members.iter().next().unwrap();
+
+        // Breadth-first from the start back to itself, within the component
+        let mut previous: HashMap<&str, &str> = HashMap::new();
+        let mut queue = VecDeque::from([start]);
+        'search: while let Some(node) = queue.pop_front() {
+            for &next in &adjacency[node] {
+                if !members.contains(next) {
+                    continue;
+                }
+                if next == start {
+                    let mut cycle = vec![node.to_string()];
+                    let mut at = node;
+                    while at != start {
+                        at = previous[at];
+                        cycle.push(at.to_string());
+                    }
+                    cycle.reverse();
+                    cycles.push(cycle);
+                    break 'search;
+                }
+                if !previous.contains_key(next) {
+                    previous.insert(next, node);
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+    cycles
+}
+
+/// Tarjan's algorithm over a graph small enough to recurse through.
+fn strongly_connected<'a>(adjacency: &BTreeMap<&'a str, Vec<&'a str>>) -> Vec<Vec<&'a str>> {
+    struct Tarjan<'a, 'g> {
+        adjacency: &'g BTreeMap<&'a str, Vec<&'a str>>,
+        index: HashMap<&'a str, usize>,
+        low: HashMap<&'a str, usize>,
+        stack: Vec<&'a str>,
+        on_stack: BTreeSet<&'a str>,
+        components: Vec<Vec<&'a str>>,
+    }
+
+    impl<'a> Tarjan<'a, '_> {
+        fn visit(&mut self, node: &'a str) {
+            let index = self.index.len();
+            self.index.insert(node, index);
+            self.low.insert(node, index);
+            self.stack.push(node);
+            self.on_stack.insert(node);
+
+            for &next in &self.adjacency[node] {
+                if !self.index.contains_key(next) {
+                    self.visit(next);
+                    let low = self.low[node].min(self.low[next]);
+                    self.low.insert(node, low);
+                } else if self.on_stack.contains(next) {
+                    let low = self.low[node].min(self.index[next]);
+                    self.low.insert(node, low);
+                }
+            }
+
+            if self.low[node] == self.index[node] {
+                let mut component = Vec::new();
+                while let Some(member) = self.stack.pop() {
+                    self.on_stack.remove(member);
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                self.components.push(component);
+            }
+        }
+    }
+
+    let mut tarjan = Tarjan {
+        adjacency,
+        index: HashMap::new(),
+        low: HashMap::new(),
+        stack: Vec::new(),
+        on_stack: BTreeSet::new(),
+        components: Vec::new(),
+    };
+    for &node in adjacency.keys() {
+        if !tarjan.index.contains_key(node) {
+            tarjan.visit(node);
+        }
+    }
+    tarjan.components
+}
+
+fn violations(
+    graph: &ModuleGraph,
+    modules: &[ModuleMetrics],
+    edges: &[Edge],
+    cycles: &[Vec<String>],
+) -> Vec<Violation> {
+    let metrics: HashMap<&str, &ModuleMetrics> =
+        modules.iter().map(|m| (m.path.as_str(), m)).collect();
+    let in_cycle: BTreeSet<&str> = cycles.iter().flatten().map(String::as_str).collect();
+    let mut violations = Vec::new();
+
+    for edge in edges {
+        let (from_package, to_package) =
+            (graph.package_dir(&edge.from), graph.package_dir(&edge.to));
+        let (from_side, to_side) = (side(&edge.from), side(&edge.to));
+
+        if from_package != to_package {
+            violations.push(Violation {
+                kind: ViolationKind::Boundary,
+                module: edge.from.clone(),
+                line: Some(edge.line),
+                message: format!(
+                    "Imports {} from another package ({}); depend on the package instead of its files",
+                    edge.to,
+                    if to_package.as_os_str().is_empty() { ".".to_string() } else { to_package.display().to_string() }
+                ),
+            });
+        } else if from_side.is_some() && to_side.is_some() && from_side != to_side {
+            violations.push(Violation {
+                kind: ViolationKind::Boundary,
+                module: edge.from.clone(),
+                line: Some(edge.line),
+                message: format!(
+                    "{} code imports {} code ({}); share it through a common module",
+                    from_side.unwrap_or_default(),
+                    to_side.unwrap_or_default(),
+                    edge.to
+                ),
+            });
+        }
+
+        // Cycles are reported on their own
+        let (from, to) = (metrics[edge.from.as_str()], metrics[edge.to.as_str()]);
+        if from.fan_in > 0
+            && to.instability - from.instability > INSTABILITY_MARGIN
+            && !in_cycle.contains(edge.from.as_str())
+        {
+            violations.push(Violation {
+                kind: ViolationKind::UnstableDependency,
+                module: edge.from.clone(),
+                line: Some(edge.line),
+                message: format!(
+                    "Depended on by {} modules (instability {:.2}) but depends on {} (instability {:.2}); depend on more stable modules",
+                    from.fan_in, from.instability, to.path, to.instability
+                ),
+            });
+        }
+    }
+
+    for module in modules.iter().filter(|m| m.fan_out > MAX_FAN_OUT) {
+        violations.push(Violation {
+            kind: ViolationKind::FanOut,
+            module: module.path.clone(),
+            line: None,
+            message: format!(
+                "Depends on {} modules (max {}); split its responsibilities",
+                module.fan_out, MAX_FAN_OUT
+            ),
+        });
+    }
+    violations
+}
+
+/// Whether `path` is client or server code, by the directories it is in.
+fn side(path: &str) -> Option<&'static str> {
+    path.split('/').find_map(|segment| {
+        if CLIENT_DIRS.contains(&segment) {
+            Some("Client")
+        } else if SERVER_DIRS.contains(&segment) {
+            Some("Server")
+        } else {
+            None
+        }
+    })
+}
@@ -0,0 +1,864 @@
+use crate::acceptance::{BrowserAction, BrowserScript, BrowserStep};
+use crate::verdict::{Finding, Severity};
+use anyhow::{anyhow, Result};
+use futures_util::stream::{SplitSink, StreamExt};
+use futures_util::SinkExt;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::process::{Child, Command};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+use tracing::{debug, info};
+
+/// Executables tried, in order, when `CHROME_PATH` is not set.
+const CHROMIUM_BINARIES: [&str; 5] = [
+    "chromium",
+    "chromium-browser",
+    "google-chrome",
+    "google-chrome-stable",
+    "chrome",
+];
+
+const LAUNCH_TIMEOUT: Duration = Duration::from_secs(20);
+/// Bound on one DevTools command.
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long a step waits for the page to reach the state it expects.
+const STEP_TIMEOUT: Duration = Duration::from_secs(10);
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+type Pending = Arc<Mutex<HashMap<u64, oneshot::Sender<Result<Value>>>>>;
+
+/// A locally installed headless Chromium driven over the DevTools
+/// protocol, so the gates can use the app the way a person would.
+/// The browser and its profile are removed on drop.
+pub struct Browser {
+    child: Child,
+    port: u16,
+    profile_dir: PathBuf,
+    http: Client,
+}
+
+/// Outcome of one browser script, with what the page complained about.
+#[derive(Debug, Clone)]
+pub struct BrowserRun {
+    pub name: String,
+    pub steps_passed: usize,
+    pub steps_total: usize,
+    pub failures: Vec<String>,
+    pub console_errors: Vec<String>,
+    pub failed_requests: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Target {
+    id: String,
+    web_socket_debugger_url: String,
+}
+
+/// The installed Chromium: `CHROME_PATH`, or the first known binary on `PATH`.
+pub fn chromium_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("CHROME_PATH") {
+        return Some(PathBuf::from(path));
+    }
+
+    let dirs = std::env::var_os("PATH")?;
+    CHROMIUM_BINARIES.iter().find_map(|binary| {
+        std::env::split_paths(&dirs)
+            .map(|dir| dir.join(binary))
+            .find(|candidate| candidate.is_file())
+    })
+}
+
+impl Browser {
+    pub async fn launch() -> Result<Self> {
+        let binary = chromium_path()
+            .ok_or_else(|| anyhow!("No Chromium found; install chromium or set CHROME_PATH"))?;
+        let profile_dir =
+            std::env::temp_dir().join(format!("wiggum-chromium-{}", uuid::Uuid::new_v4()));
+
+        let mut child = Command::new(&binary)
+            .args([
+                "--headless=new",
+                "--remote-debugging-port=0",
+                "--no-first-run",
+                "--no-default-browser-check",
+                "--disable-gpu",
+                "--disable-extensions",
+                "--window-size=1280,800",
+            ])
+            .arg(format!("--user-data-dir={}", profile_dir.display()))
+            .arg("about:blank")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| anyhow!("Could not start {}: {}", binary.display(), e))?;
+
+        // Chromium announces its DevTools endpoint on stderr
+        let stderr = child
+            .stderr
+            .take()
+            .ok_or_else(|| anyhow!("Chromium stderr not captured"))?;
+        let mut lines = BufReader::new(stderr).lines();
+        let port = tokio::time::timeout(LAUNCH_TIMEOUT, async {
+            while let Some(line) = lines.next_line().await? {
+                if let Some(port) = devtools_port(&line) {
+                    return Ok(port);
+                }
+            }
+            Err(anyhow!("Chromium exited before opening DevTools"))
+        })
+        .await
+        .map_err(|_| {
+            anyhow!(
+                "Chromium did not open DevTools within {}s",
+                LAUNCH_TIMEOUT.as_secs()
+            )
+        })??;
+
+        // Keep draining stderr so a chatty browser never blocks on the pipe
+        tokio::spawn(async move { while let Ok(Some(_)) = lines.next_line().await {} });
+
+        info!(
+            "Started headless {} with DevTools on port {}",
+            binary.display(),
+            port
+        );
+        Ok(Self {
+            child,
+            port,
+            profile_dir,
+            http: Client::new(),
+        })
+    }
+
+    /// Open a blank tab; it is closed when the `Page` is dropped.
+    pub async fn new_page(&self) -> Result<Page> {
+        let target: Target = self
+            .http
+            .put(format!(
+                "http://127.0.0.1:{}/json/new?about:blank",
+                self.port
+            ))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let close_url = format!("http://127.0.0.1:{}/json/close/{}", self.port, target.id);
+        Page::connect(&target.web_socket_debugger_url, close_url).await
+    }
+
+    /// Run a script in a fresh tab against the app at `base_url`.
+    pub async fn run_script(&self, base_url: &str, script: &BrowserScript) -> Result<BrowserRun> {
+        self.new_page().await?.run(base_url, script).await
+    }
+}
+
+impl Drop for Browser {
+    fn drop(&mut self) {
+        let _ = self.child.start_kill();
+        let _ = std::fs::remove_dir_all(&self.profile_dir);
+        debug!("Stopped headless Chromium on port {}", self.port);
+    }
+}
+
+impl BrowserRun {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "browser \"{}\": {}/{} steps passed, {} console errors, {} failed requests",
+            self.name,
+            self.steps_passed,
+            self.steps_total,
+            self.console_errors.len(),
+            self.failed_requests.len()
+        )
+    }
+
+    /// Console errors and failed requests seen while the script ran. Step
+    /// failures are reported by whoever owns the script.
+    pub fn findings(&self) -> Vec<Finding> {
+        let console = self.console_errors.iter().map(|error| {
+            Finding::new(
+                Severity::Major,
+                format!("Console error during \"{}\": {}", self.name, error),
+            )
+            .rule("browser:console")
+        });
+        let network = self.failed_requests.iter().map(|request| {
+            Finding::new(
+                Severity::Major,
+                format!("Request failed during \"{}\": {}", self.name, request),
+            )
+            .rule("browser:network")
+        });
+        console.chain(network).collect()
+    }
+}
+
+/// Port from `DevTools listening on ws://127.0.0.1:PORT/devtools/browser/...`.
+fn devtools_port(line: &str) -> Option<u16> {
+    let url = line.strip_prefix("DevTools listening on ")?;
+    let authority = url.trim().strip_prefix("ws://")?.split('/').next()?;
+    authority.rsplit(':').next()?.parse().ok()
+}
+
+/// What the page reported while a script ran.
+#[derive(Default)]
+struct PageEvents {
+    console_errors: Vec<String>,
+    failed_requests: Vec<String>,
+    /// requestId -> `METHOD url`, to name failed requests
+    requests: HashMap<String, String>,
+}
+
+/// Console errors and failed requests a page reported.
+#[derive(Debug, Clone, Default)]
+pub struct PageProblems {
+    pub console_errors: Vec<String>,
+    pub failed_requests: Vec<String>,
+}
+
+/// The page's accessibility tree as an indented outline. Elements a user
+/// can act on are numbered (`[3] button "Add"`) so a model can refer to them.
+#[derive(Debug, Clone, Default)]
+pub struct AxSnapshot {
+    pub outline: String,
+    /// `[n]` -> backend DOM node id
+    refs: Vec<i64>,
+    /// `[n]` -> `role "name"`, for describing steps
+    labels: Vec<String>,
+}
+
+impl AxSnapshot {
+    pub fn node(&self, reference: usize) -> Option<i64> {
+        self.refs.get(reference.checked_sub(1)?).copied()
+    }
+
+    pub fn label(&self, reference: usize) -> Option<&str> {
+        self.labels
+            .get(reference.checked_sub(1)?)
+            .map(String::as_str)
+    }
+}
+
+/// One tab's DevTools session: commands are matched to replies by id,
+/// events are folded into `PageEvents` as they arrive.
+pub struct Page {
+    sink: tokio::sync::Mutex<SplitSink<Socket, Message>>,
+    next_id: AtomicU64,
+    pending: Pending,
+    events: Arc<Mutex<PageEvents>>,
+    reader: JoinHandle<()>,
+    close_url: String,
+}
+
+impl Page {
+    async fn connect(url: &str, close_url: String) -> Result<Self> {
+        let (socket, _) = connect_async(url).await?;
+        let (sink, mut stream) = socket.split();
+        let pending: Pending = Arc::new(Mutex::new(HashMap::new()));
+        let events = Arc::new(Mutex::new(PageEvents::default()));
+
+        let reader = {
+            let pending = pending.clone();
+            let events = events.clone();
+            tokio::spawn(async move {
+                while let Some(Ok(message)) = stream.next().await {
+                    let Message::Text(text) = message else {
+                        continue;
+                    };
+                    let Ok(message) = serde_json::from_str::<Value>(&text) else {
+                        continue;
+                    };
+
+                    match message.get("id").and_then(Value::as_u64) {
+                        Some(id) => {
+                            let reply = match message.get("error") {
+                                Some(error) => Err(anyhow!(
+                                    "DevTools error: {}",
+                                    error["message"].as_str().unwrap_or("unknown")
+                                )),
+                                None => Ok(message.get("result").cloned().unwrap_or(Value::Null)),
+                            };
+                            if let Some(sender) = pending.lock().unwrap().remove(&id) {
+                                let _ = sender.send(reply);
+                            }
+                        }
+                        None => record_event(&mut events.lock().unwrap(), &message),
+                    }
+                }
+            })
+        };
+
+        let page = Self {
+            sink: tokio::sync::Mutex::new(sink),
+            next_id: AtomicU64::new(1),
+            pending,
+            events,
+            reader,
+            close_url,
+        };
+        for domain in ["Page", "Runtime", "Log", "Network", "DOM", "Accessibility"] {
+            page.call(&format!("{}.enable", domain), json!({})).await?;
+        }
+        Ok(page)
+    }
+
+    async fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id, sender);
+
+        let request = json!({ "id": id, "method": method, "params": params });
+        self.sink
+            .lock()
+            .await
+            .send(Message::Text(request.to_string()))
+            .await?;
+
+        match tokio::time::timeout(COMMAND_TIMEOUT, receiver).await {
+            Ok(Ok(reply)) => reply,
+            Ok(Err(_)) => Err(anyhow!("DevTools connection closed during {}", method)),
+            Err(_) => {
+                self.pending.lock().unwrap().remove(&id);
+                Err(anyhow!(
+                    "{} timed out after {}s",
+                    method,
+                    COMMAND_TIMEOUT.as_secs()
+                ))
+            }
+        }
+    }
+
+    /// Evaluate an expression in the page and return its value; promises are awaited.
+    async fn evaluate(&self, expression: &str) -> Result<Value> {
+        let result = self
+            .call(
+                "Runtime.evaluate",
+                json!({
+                    "expression": expression,
+                    "awaitPromise": true,
+                    "returnByValue": true,
+                }),
+            )
+            .await?;
+
+        if let Some(details) = result.get("exceptionDetails") {
+            let message = details["exception"]["description"]
+                .as_str()
+                .or_else(|| details["text"].as_str())
+                .unwrap_or("exception");
+            return Err(anyhow!("Script error: {}", message));
+        }
+        Ok(result["result"]["value"].clone())
+    }
+
+    async fn run(&self, base_url: &str, script: &BrowserScript) -> Result<BrowserRun> {
+        let mut run = BrowserRun {
+            name: script.name.clone(),
+            steps_passed: 0,
+            steps_total: script.steps.len(),
+            failures: Vec::new(),
+            console_errors: Vec::new(),
+            failed_requests: Vec::new(),
+        };
+
+        for (index, step) in script.steps.iter().enumerate() {
+            match self.step(base_url, step).await {
+                Ok(()) => run.steps_passed += 1,
+                Err(e) => {
+                    // Later steps depend on this one; stop here
+                    run.failures
+                        .push(format!("step {} ({:?}): {}", index + 1, step.action, e));
+                    break;
+                }
+            }
+        }
+
+        // Let in-flight requests settle before collecting what failed
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        let problems = self.take_problems();
+        run.console_errors = problems.console_errors;
+        run.failed_requests = problems.failed_requests;
+
+        debug!("{}", run.summary());
+        Ok(run)
+    }
+
+    async fn step(&self, base_url: &str, step: &BrowserStep) -> Result<()> {
+        let selector = || {
+            step.selector
+                .as_deref()
+                .ok_or_else(|| anyhow!("{:?} needs a selector", step.action))
+        };
+        let value = || {
+            step.value
+                .as_deref()
+                .ok_or_else(|| anyhow!("{:?} needs a value", step.action))
+        };
+
+        match step.action {
+            BrowserAction::Goto => self.navigate(&join_url(base_url, value()?)).await,
+            BrowserAction::WaitFor | BrowserAction::ExpectVisible => {
+                let selector = selector()?;
+                self.wait_until(
+                    &format!("{}({})", VISIBLE_JS, js_string(selector)),
+                    &format!("{} to be visible", selector),
+                )
+                .await
+            }
+            BrowserAction::ExpectText => {
+                let text = value()?;
+                let scope = match &step.selector {
+                    Some(selector) => format!("document.querySelector({})", js_string(selector)),
+                    None => "document.body".to_string(),
+                };
+                let condition = format!(
+                    "(() => {{ const el = {}; return !!el && el.innerText.includes({}); }})()",
+                    scope,
+                    js_string(text)
+                );
+                self.wait_until(&condition, &format!("text \"{}\"", text))
+                    .await
+            }
+            BrowserAction::Click => {
+                let selector = selector()?;
+                let (x, y) = self.element_center(selector).await?;
+                self.click_at(x, y).await
+            }
+            BrowserAction::Fill => {
+                let selector = selector()?;
+                self.element_center(selector).await?;
+                // Clear the field the way a select-all and delete would, then type
+                self.evaluate(&format!(
+                    "(() => {{ const el = document.querySelector({}); el.focus(); if ('value' in el) {{ el.value = ''; el.dispatchEvent(new Event('input', {{ bubbles: true }})); }} }})()",
+                    js_string(selector)
+                )).await?;
+                self.call("Input.insertText", json!({ "text": value()? }))
+                    .await?;
+                Ok(())
+            }
+            BrowserAction::Press => self.press(value()?).await,
+        }
+    }
+
+    /// Load `url` and wait for it to finish loading.
+    pub async fn navigate(&self, url: &str) -> Result<()> {
+        let navigated = self.call("Page.navigate", json!({ "url": url })).await?;
+        if let Some(error) = navigated["errorText"].as_str() {
+            return Err(anyhow!("could not load {}: {}", url, error));
+        }
+        self.wait_until(
+            "document.readyState === 'complete' && location.href !== 'about:blank'",
+            &format!("{} to finish loading", url),
+        )
+        .await
+    }
+
+    pub async fn url(&self) -> Result<String> {
+        Ok(self
+            .evaluate("location.href")
+            .await?
+            .as_str()
+            .unwrap_or_default()
+            .to_string())
+    }
+
+    /// Type a key such as `Enter`, `Tab` or `a` into the focused element.
+    pub async fn press(&self, key: &str) -> Result<()> {
+        let (key, code, key_code, text) = key_definition(key);
+        self.call("Input.dispatchKeyEvent", json!({
+            "type": "keyDown", "key": key, "code": code, "windowsVirtualKeyCode": key_code, "text": text,
+        })).await?;
+        self.call(
+            "Input.dispatchKeyEvent",
+            json!({
+                "type": "keyUp", "key": key, "code": code, "windowsVirtualKeyCode": key_code,
+            }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Click the middle of a node from an `AxSnapshot`.
+    pub async fn click_node(&self, backend_node_id: i64) -> Result<()> {
+        self.call(
+            "DOM.scrollIntoViewIfNeeded",
+            json!({ "backendNodeId": backend_node_id }),
+        )
+        .await?;
+        let model = self
+            .call(
+                "DOM.getBoxModel",
+                json!({ "backendNodeId": backend_node_id }),
+            )
+            .await?;
+        let quad: Vec<f64> = model["model"]["content"]
+            .as_array()
+            .map(|points| points.iter().filter_map(Value::as_f64).collect())
+            .unwrap_or_default();
+        if quad.len() < 8 {
+            return Err(anyhow!("element has no box to click"));
+        }
+
+        let x = (quad[0] + quad[2] + quad[4] + quad[6]) / 4.0;
+        let y = (quad[1] + quad[3] + quad[5] + quad[7]) / 4.0;
+        self.click_at(x, y).await
+    }
+
+    /// Replace the value of a node from an `AxSnapshot` by typing `text`.
+    pub async fn fill_node(&self, backend_node_id: i64, text: &str) -> Result<()> {
+        let resolved = self
+            .call(
+                "DOM.resolveNode",
+                json!({ "backendNodeId": backend_node_id }),
+            )
+            .await?;
+        let object_id = resolved["object"]["objectId"]
+            .as_str()
+            .ok_or_else(|| anyhow!("element is gone"))?;
+        self.call("Runtime.callFunctionOn", json!({
+            "objectId": object_id,
+            "functionDeclaration": "function() { this.focus(); if ('value' in this) { this.value = ''; this.dispatchEvent(new Event('input', { bubbles: true })); } }",
+        })).await?;
+        self.call("Input.insertText", json!({ "text": text }))
+            .await?;
+        Ok(())
+    }
+
+    /// The accessibility tree as the user's assistive technology would see it.
+    pub async fn accessibility_tree(&self) -> Result<AxSnapshot> {
+        let tree = self.call("Accessibility.getFullAXTree", json!({})).await?;
+        let nodes = tree["nodes"].as_array().cloned().unwrap_or_default();
+        Ok(render_ax_tree(&nodes))
+    }
+
+    /// Console errors and failed requests since the last call.
+    pub fn take_problems(&self) -> PageProblems {
+        let mut events = self.events.lock().unwrap();
+        PageProblems {
+            console_errors: std::mem::take(&mut events.console_errors),
+            failed_requests: std::mem::take(&mut events.failed_requests),
+        }
+    }
+
+    async fn click_at(&self, x: f64, y: f64) -> Result<()> {
+        for kind in ["mouseMoved", "mousePressed", "mouseReleased"] {
+            self.call(
+                "Input.dispatchMouseEvent",
+                json!({
+                    "type": kind, "x": x, "y": y, "button": "left", "clickCount": 1,
+                }),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Wait for `selector` to be visible, scroll it into view and return
+    /// the middle of its box in viewport coordinates.
+    async fn element_center(&self, selector: &str) -> Result<(f64, f64)> {
+        self.wait_until(
+            &format!("{}({})", VISIBLE_JS, js_string(selector)),
+            &format!("{} to be visible", selector),
+        )
+        .await?;
+
+        let point = self.evaluate(&format!(
+            "(() => {{ const el = document.querySelector({}); el.scrollIntoView({{ block: 'center' }}); const r = el.getBoundingClientRect(); return [r.x + r.width / 2, r.y + r.height / 2]; }})()",
+            js_string(selector)
+        )).await?;
+
+        match (point[0].as_f64(), point[1].as_f64()) {
+            (Some(x), Some(y)) => Ok((x, y)),
+            _ => Err(anyhow!("could not locate {}", selector)),
+        }
+    }
+
+    /// Poll a boolean expression until it holds or `STEP_TIMEOUT` passes.
+    async fn wait_until(&self, condition: &str, what: &str) -> Result<()> {
+        let deadline = Instant::now() + STEP_TIMEOUT;
+        loop {
+            match self.evaluate(condition).await {
+                Ok(Value::Bool(true)) => return Ok(()),
+                // Evaluating mid-navigation fails; treat it like "not yet"
+                Ok(_) | Err(_) if Instant::now() < deadline => {
+                    tokio::time::sleep(POLL_INTERVAL).await
+                }
+                Ok(_) => {
+                    return Err(anyhow!(
+                        "timed out after {}s waiting for {}",
+                        STEP_TIMEOUT.as_secs(),
+                        what
+                    ))
+                }
+                Err(e) => return Err(anyhow!("waiting for {}: {}", what, e)),
+            }
+        }
+    }
+}
+
+impl Drop for Page {
+    fn drop(&mut self) {
+        self.reader.abort();
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            let close_url = std::mem::take(&mut self.close_url);
+            runtime.spawn(async move {
+                let _ = Client::new().get(close_url).send().await;
+            });
+        }
+    }
+}
+
+/// Roles a user can act on; these get a reference number.
+const INTERACTIVE_ROLES: [&str; 15] = [
+    "button",
+    "link",
+    "textbox",
+    "searchbox",
+    "checkbox",
+    "radio",
+    "combobox",
+    "listbox",
+    "option",
+    "menuitem",
+    "tab",
+    "switch",
+    "slider",
+    "spinbutton",
+    "menuitemcheckbox",
+];
+/// Roles that only wrap other nodes; shown when they carry a name.
+const STRUCTURAL_ROLES: [&str; 6] = [
+    "generic",
+    "none",
+    "presentation",
+    "InlineTextBox",
+    "LineBreak",
+    "RootWebArea",
+];
+/// Outline lines given to a model; the rest is cut.
+const MAX_OUTLINE_LINES: usize = 400;
+/// States worth showing next to a node.
+const AX_STATES: [&str; 7] = [
+    "focused", "disabled", "checked", "expanded", "selected", "required", "invalid",
+];
+
+fn render_ax_tree(nodes: &[Value]) -> AxSnapshot {
+    let by_id: HashMap<&str, &Value> = nodes
+        .iter()
+        .filter_map(|node| Some((node["nodeId"].as_str()?, node)))
+        .collect();
+
+    let mut snapshot = AxSnapshot::default();
+    let mut lines = Vec::new();
+    if let Some(root) = nodes.first() {
+        outline_node(root, 0, &by_id, &mut snapshot, &mut lines);
+    }
+
+    if lines.len() > MAX_OUTLINE_LINES {
+        let hidden = lines.len() - MAX_OUTLINE_LINES;
+        lines.truncate(MAX_OUTLINE_LINES);
+        lines.push(format!("... {} more nodes not shown", hidden));
+    }
+    snapshot.outline = lines.join("\n");
+    snapshot
+}
+
+fn outline_node(
+    node: &Value,
+    depth: usize,
+    by_id: &HashMap<&str, &Value>,
+    snapshot: &mut AxSnapshot,
+    lines: &mut Vec<String>,
+) {
+    let role = node["role"]["value"].as_str().unwrap_or("");
+    let name = node["name"]["value"].as_str().unwrap_or("").trim();
+    let ignored = node["ignored"].as_bool().unwrap_or(false);
+
+    let hollow = name.is_empty() && (STRUCTURAL_ROLES.contains(&role) || role == "StaticText");
+    let shown = !ignored && !hollow;
+
+    let mut child_depth = depth;
+    if shown {
+        let mut line = "  ".repeat(depth);
+        let label = if name.is_empty() {
+            role.to_string()
+        } else {
+            format!("{} \"{}\"", role, name)
+        };
+
+        match node["backendDOMNodeId"]
+            .as_i64()
+            .filter(|_| INTERACTIVE_ROLES.contains(&role))
+        {
+            Some(backend) => {
+                snapshot.refs.push(backend);
+                snapshot.labels.push(label.clone());
+                line.push_str(&format!("[{}] {}", snapshot.refs.len(), label));
+            }
+            None => line.push_str(&label),
+        }
+
+        if let Some(value) = node["value"]["value"].as_str().filter(|v| !v.is_empty()) {
+            line.push_str(&format!(" value=\"{}\"", value));
+        }
+        for property in node["properties"].as_array().into_iter().flatten() {
+            let property_name = property["name"].as_str().unwrap_or("");
+            let value = &property["value"]["value"];
+            if AX_STATES.contains(&property_name)
+                && value != &Value::Bool(false)
+                && value != "false"
+            {
+                line.push_str(&format!(" ({})", property_name));
+            }
+        }
+
+        lines.push(line);
+        child_depth += 1;
+    }
+
+    for child in node["childIds"].as_array().into_iter().flatten() {
+        if let Some(child) = child.as_str().and_then(|id| by_id.get(id)) {
+            outline_node(child, child_depth, by_id, snapshot, lines);
+        }
+    }
+}
+
+/// `(selector) => bool`: the element exists and takes up space on screen.
+const VISIBLE_JS: &str = "((selector) => { const el = document.querySelector(selector); if (!el) return false; const r = el.getBoundingClientRect(); const s = getComputedStyle(el); return r.width > 0 && r.height > 0 && s.visibility !== 'hidden' && s.display !== 'none'; })";
+
+fn record_event(events: &mut PageEvents, message: &Value) {
+    let params = &message["params"];
+    match message["method"].as_str().unwrap_or_default() {
+        "Runtime.consoleAPICalled" if params["type"] == "error" => {
+            let text = params["args"]
+                .as_array()
+                .map(|args| {
+                    args.iter()
+                        .map(remote_object_text)
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                })
+                .unwrap_or_default();
+            events.console_errors.push(text);
+        }
+        "Runtime.exceptionThrown" => {
+            let details = &params["exceptionDetails"];
+            let text = details["exception"]["description"]
+                .as_str()
+                .or_else(|| details["text"].as_str())
+                .unwrap_or("Uncaught exception");
+            events
+                .console_errors
+                .push(text.lines().next().unwrap_or(text).to_string());
+        }
+        "Log.entryAdded"
+            if params["entry"]["level"] == "error" && params["entry"]["source"] != "network" =>
+        {
+            if let Some(text) = params["entry"]["text"].as_str() {
+                events.console_errors.push(text.to_string());
+            }
+        }
+        "Network.requestWillBeSent" => {
+            if let (Some(id), Some(method), Some(url)) = (
+                params["requestId"].as_str(),
+                params["request"]["method"].as_str(),
+                params["request"]["url"].as_str(),
+            ) {
+                events
+                    .requests
+                    .insert(id.to_string(), format!("{} {}", method, url));
+            }
+        }
+        "Network.responseReceived" => {
+            let status = params["response"]["status"].as_u64().unwrap_or(0);
+            if status >= 400 {
+                let url = params["response"]["url"].as_str().unwrap_or("unknown url");
+                let request = params["requestId"]
+                    .as_str()
+                    .and_then(|id| events.requests.get(id).cloned())
+                    .unwrap_or_else(|| url.to_string());
+                events
+                    .failed_requests
+                    .push(format!("{} -> {}", request, status));
+            }
+        }
+        "Network.loadingFailed" if params["canceled"] != true => {
+            let request = params["requestId"]
+                .as_str()
+                .and_then(|id| events.requests.get(id).cloned())
+                .unwrap_or_else(|| "request".to_string());
+            let error = params["errorText"].as_str().unwrap_or("failed");
+            events
+                .failed_requests
+                .push(format!("{} -> {}", request, error));
+        }
+        _ => {}
+    }
+}
+
+fn remote_object_text(object: &Value) -> String {
+    match object.get("value") {
+        Some(Value::String(text)) => text.clone(),
+        Some(value) => value.to_string(),
+        None => object["description"].as_str().unwrap_or("").to_string(),
+    }
+}
+
+fn js_string(text: &str) -> String {
+    Value::String(text.to_string()).to_string()
+}
+
+/// Paths are relative to the app; absolute URLs are used as given.
+fn join_url(base_url: &str, target: &str) -> String {
+    if target.starts_with("http://") || target.starts_with("https://") {
+        return target.to_string();
+    }
+    format!(
+        "{}/{}",
+        base_url.trim_end_matches('/'),
+        target.trim_start_matches('/')
+    )
+}
+
+/// DevTools key event fields for a key name such as `Enter`.
+fn key_definition(key: &str) -> (&str, String, u32, String) {
+    match key {
+        "Enter" => ("Enter", "Enter".into(), 13, "\r".into()),
+        "Tab" => ("Tab", "Tab".into(), 9, String::new()),
+        "Escape" => ("Escape", "Escape".into(), 27, String::new()),
+        "Backspace" => ("Backspace", "Backspace".into(), 8, String::new()),
+        "Delete" => ("Delete", "Delete".into(), 46, String::new()),
+        "ArrowUp" => ("ArrowUp", "ArrowUp".into(), 38, String::new()),
+        "ArrowDown" => ("ArrowDown", "ArrowDown".into(), 40, String::new()),
+        "ArrowLeft" => ("ArrowLeft", "ArrowLeft".into(), 37, String::new()),
+        "ArrowRight" => ("ArrowRight", "ArrowRight".into(), 39, String::new()),
+        " " | "Space" => (" ", "Space".into(), 32, " ".into()),
+        other => {
+            let upper = other.to_uppercase();
+            let key_code = upper.chars().next().map(|c| c as u32).unwrap_or(0);
+            (other, format!("Key{}", upper), key_code, other.to_string())
+        }
+    }
+}
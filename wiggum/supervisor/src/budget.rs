@@ -0,0 +1,242 @@
+use crate::llm::{ChatMessage, ToolDefinition};
+use tracing::{debug, warn};
+
+/// Used when neither the server nor models.json knows a model's window.
+/// Small on purpose: overestimating it is what truncates replies.
+pub const DEFAULT_CONTEXT_LENGTH: usize = 8192;
+
+/// Per-message framing (role, separators) added by chat templates.
+const MESSAGE_OVERHEAD: usize = 4;
+
+/// Sections that would shrink below this are dropped instead.
+const MIN_SECTION_TOKENS: usize = 64;
+
+const OMITTED_MESSAGE: &str = "[earlier output omitted to fit the context window]";
+
+/// Rough token count without a tokenizer: about four characters per token
+/// for English and code, rounded up so the estimate errs on the large side.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+pub fn message_tokens(message: &ChatMessage) -> usize {
+    let calls = message
+        .tool_calls
+        .iter()
+        .flatten()
+        .map(|call| {
+            estimate_tokens(&call.function.name) + estimate_tokens(&call.function.arguments)
+        })
+        .sum::<usize>();
+    MESSAGE_OVERHEAD + message.content.as_deref().map_or(0, estimate_tokens) + calls
+}
+
+pub fn tools_tokens(tools: &[ToolDefinition]) -> usize {
+    serde_json::to_string(tools).map_or(0, |json| estimate_tokens(&json))
+}
+
+/// Cut `text` to roughly `tokens`, keeping its head and tail and saying
+/// how much was left out.
+pub fn truncate_to_tokens(text: &str, tokens: usize) -> String {
+    let chars = text.chars().count();
+    let keep = tokens * 4;
+    if chars <= keep {
+        return text.to_string();
+    }
+
+    let note = format!(
+        "\n[... {} characters omitted to fit the context window ...]\n",
+        chars - keep
+    );
+    let keep = keep.saturating_sub(note.len());
+    let head = keep * 2 / 3;
+    let tail = keep - head;
+
+    let mut cut: String = text.chars().take(head).collect();
+    cut.push_str(&note);
+    cut.extend(text.chars().skip(chars - tail));
+    cut
+}
+
+/// How much a prompt section matters when the budget is tight. Lower
+/// priorities are cut first; `Required` sections are never touched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Low,
+    Normal,
+    High,
+    Required,
+}
+
+#[derive(Debug, Clone)]
+struct PromptSection {
+    title: String,
+    body: String,
+    priority: Priority,
+}
+
+impl PromptSection {
+    fn render(&self) -> String {
+        if self.title.is_empty() {
+            self.body.clone()
+        } else {
+            format!("{}:\n{}", self.title, self.body)
+        }
+    }
+}
+
+/// Prompt assembled from prioritised sections, rendered in the order they
+/// were added but shrunk lowest priority first to fit a token budget.
+#[derive(Debug, Clone, Default)]
+pub struct PromptBuilder {
+    sections: Vec<PromptSection>,
+}
+
+impl PromptBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a section; an empty `title` renders the body on its own.
+    pub fn section(mut self, title: &str, body: impl Into<String>, priority: Priority) -> Self {
+        let body = body.into();
+        if !body.trim().is_empty() {
+            self.sections.push(PromptSection {
+                title: title.to_string(),
+                body,
+                priority,
+            });
+        }
+        self
+    }
+
+    pub fn render(&self, budget: usize) -> String {
+        let mut sections = self.sections.clone();
+        let mut total: usize = sections.iter().map(|s| estimate_tokens(&s.render())).sum();
+
+        let mut order: Vec<usize> = (0..sections.len())
+            .filter(|&i| sections[i].priority != Priority::Required)
+            .collect();
+        // Stable sort: among equal priorities the earliest section goes first
+        order.sort_by_key(|&i| sections[i].priority);
+
+        for index in order {
+            if total <= budget {
+                break;
+            }
+
+            let section = &mut sections[index];
+            let size = estimate_tokens(&section.render());
+            let target = size.saturating_sub(total - budget);
+
+            if target >= MIN_SECTION_TOKENS {
+                debug!(
+                    "Truncating prompt section {:?} from {} to {} tokens",
+                    section.title, size, target
+                );
+                section.body = truncate_to_tokens(
+                    &section.body,
+                    target.saturating_sub(estimate_tokens(&section.title) + 1),
+                );
+            } else {
+                debug!(
+                    "Dropping prompt section {:?} ({} tokens)",
+                    section.title, size
+                );
+                section.body.clear();
+            }
+            total = total - size + estimate_tokens(&section.render());
+        }
+
+        if total > budget {
+            warn!(
+                "Prompt needs ~{} tokens after trimming, over its budget of {}",
+                total, budget
+            );
+        }
+
+        sections
+            .iter()
+            .filter(|s| !s.body.is_empty())
+            .map(PromptSection::render)
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+/// Shrink `messages` in place until they fit `budget` tokens.
+///
+/// The system prompt, the opening user message (the task) and the latest
+/// message are kept; older tool results
+/// go first, then older assistant and user turns, each replaced by a short
+/// note so tool call ids still pair up. If that is not enough, the largest
+/// remaining message is truncated. Returns the estimated size afterwards.
+pub fn fit_messages(messages: &mut [ChatMessage], budget: usize) -> usize {
+    let mut total: usize = messages.iter().map(message_tokens).sum();
+    if total <= budget || messages.is_empty() {
+        return total;
+    }
+
+    let original = total;
+    let last = messages.len() - 1;
+    let task = messages.iter().position(|m| m.role == "user");
+    let protected = |index: usize, message: &ChatMessage| {
+        index == last || Some(index) == task || message.role == "system"
+    };
+
+    for role in ["tool", "assistant", "user"] {
+        for (index, message) in messages.iter_mut().enumerate() {
+            if total <= budget {
+                break;
+            }
+
+            if message.role != role || protected(index, message) {
+                continue;
+            }
+            if message.content.as_deref().map_or(0, estimate_tokens)
+                <= estimate_tokens(OMITTED_MESSAGE)
+            {
+                continue;
+            }
+
+            let size = message_tokens(message);
+            message.content = Some(OMITTED_MESSAGE.to_string());
+            total = total - size + message_tokens(message);
+        }
+    }
+
+    while total > budget {
+        let Some(index) = (0..messages.len()).max_by_key(|&i| message_tokens(&messages[i])) else {
+            break;
+        };
+        let message = &mut messages[index];
+        let Some(content) = message.content.as_deref() else {
+            break;
+        };
+
+        let size = message_tokens(message);
+        let content_tokens = estimate_tokens(content);
+        let target = content_tokens
+            .saturating_sub(total - budget)
+            .max(MIN_SECTION_TOKENS);
+        if target >= content_tokens {
+            break;
+        }
+
+        message.content = Some(truncate_to_tokens(content, target));
+        total = total - size + message_tokens(message);
+    }
+
+    if total > budget {
+        warn!(
+            "Conversation needs ~{} tokens, over the budget of {} even after trimming",
+            total, budget
+        );
+    } else {
+        debug!(
+            "Trimmed conversation from ~{} to ~{} tokens",
+            original, total
+        );
+    }
+    total
+}
@@ -0,0 +1,259 @@
+use crate::llm::{ChatCompletionRequest, ChatMessage, LlmError};
+use anyhow::Result;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use tracing::{debug, info};
+
+/// Key under which the server's model list is stored.
+const MODELS_KEY: &str = "models";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum CassetteMode {
+    /// Talk to the real backend and save every exchange
+    Record,
+    /// Serve saved exchanges only; a request that was never recorded is an error
+    Replay,
+}
+
+/// Saved exchanges for one request key, served in the order they were recorded.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Track {
+    /// First user message, to make cassette files and misses readable
+    #[serde(default)]
+    prompt: String,
+    replies: Vec<serde_json::Value>,
+    #[serde(skip)]
+    cursor: usize,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Tape {
+    tracks: BTreeMap<String, Track>,
+}
+
+/// Record/replay store for LLM traffic so supervisor sessions can run
+/// offline and deterministically (e.g. in CI).
+///
+/// Requests are keyed by a hash of their normalised JSON: the streaming
+/// flag is ignored and volatile text (timestamps, runtimes, ids) is masked,
+/// so the same agent prompt maps to the same key across runs.
+#[derive(Debug)]
+pub struct Cassette {
+    path: PathBuf,
+    mode: CassetteMode,
+    tape: Mutex<Tape>,
+}
+
+impl Cassette {
+    pub fn open(path: &Path, mode: CassetteMode) -> Result<Self> {
+        let tape = if path.exists() {
+            let content = fs::read_to_string(path)?;
+            serde_json::from_str(&content)?
+        } else if mode == CassetteMode::Replay {
+            return Err(anyhow::anyhow!(
+                "Cassette {} does not exist",
+                path.display()
+            ));
+        } else {
+            Tape::default()
+        };
+
+        info!("Cassette {} opened for {:?}", path.display(), mode);
+        Ok(Self {
+            path: path.to_path_buf(),
+            mode,
+            tape: Mutex::new(tape),
+        })
+    }
+
+    pub fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+
+    /// Recorded reply for `request` when replaying; `None` when recording.
+    pub fn replay(&self, request: &ChatCompletionRequest) -> Result<Option<ChatMessage>> {
+        if self.mode != CassetteMode::Replay {
+            return Ok(None);
+        }
+
+        let key = request_key(request)?;
+        let reply = self.next_reply(&key, || first_user_message(request))?;
+        Ok(Some(serde_json::from_value(reply)?))
+    }
+
+    pub fn record(&self, request: &ChatCompletionRequest, reply: &ChatMessage) -> Result<()> {
+        if self.mode != CassetteMode::Record {
+            return Ok(());
+        }
+
+        let key = request_key(request)?;
+        self.append(
+            key,
+            first_user_message(request),
+            serde_json::to_value(reply)?,
+        )
+    }
+
+    pub fn replay_embeddings(
+        &self,
+        model: &str,
+        input: &[String],
+    ) -> Result<Option<Vec<Vec<f32>>>> {
+        if self.mode != CassetteMode::Replay {
+            return Ok(None);
+        }
+
+        let key = embeddings_key(model, input)?;
+        let reply = self.next_reply(&key, || input.first().cloned().unwrap_or_default())?;
+        Ok(Some(serde_json::from_value(reply)?))
+    }
+
+    pub fn record_embeddings(
+        &self,
+        model: &str,
+        input: &[String],
+        vectors: &[Vec<f32>],
+    ) -> Result<()> {
+        if self.mode != CassetteMode::Record {
+            return Ok(());
+        }
+
+        let key = embeddings_key(model, input)?;
+        self.append(
+            key,
+            input.first().cloned().unwrap_or_default(),
+            serde_json::to_value(vectors)?,
+        )
+    }
+
+    pub fn replay_models(&self) -> Result<Option<Vec<String>>> {
+        if self.mode != CassetteMode::Replay {
+            return Ok(None);
+        }
+
+        let reply = self.next_reply(MODELS_KEY, String::new)?;
+        Ok(Some(serde_json::from_value(reply)?))
+    }
+
+    pub fn record_models(&self, models: &[String]) -> Result<()> {
+        if self.mode != CassetteMode::Record {
+            return Ok(());
+        }
+
+        self.append(
+            MODELS_KEY.to_string(),
+            String::new(),
+            serde_json::to_value(models)?,
+        )
+    }
+
+    /// Serve the next reply on `key`'s track. Once a track is used up its
+    /// last reply repeats, so polling calls stay stable.
+    fn next_reply(&self, key: &str, prompt: impl FnOnce() -> String) -> Result<serde_json::Value> {
+        let mut tape = self.tape.lock().unwrap();
+
+        let Some(track) = tape.tracks.get_mut(key).filter(|t| !t.replies.is_empty()) else {
+            return Err(LlmError::CassetteMiss {
+                key: key.to_string(),
+                prompt: prompt().chars().take(200).collect(),
+            }
+            .into());
+        };
+
+        let index = track.cursor.min(track.replies.len() - 1);
+        track.cursor += 1;
+        debug!("Cassette replaying {} #{}", key, index);
+        Ok(track.replies[index].clone())
+    }
+
+    fn append(&self, key: String, prompt: String, reply: serde_json::Value) -> Result<()> {
+        let mut tape = self.tape.lock().unwrap();
+        let track = tape.tracks.entry(key).or_default();
+        track.prompt = prompt;
+        track.replies.push(reply);
+
+        // Write through so an aborted session still leaves a usable cassette
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&*tape)?)?;
+        Ok(())
+    }
+}
+
+fn first_user_message(request: &ChatCompletionRequest) -> String {
+    request
+        .messages
+        .iter()
+        .find(|m| m.role == "user")
+        .and_then(|m| m.content.clone())
+        .unwrap_or_default()
+}
+
+/// Patterns for text that changes between otherwise identical runs.
+fn volatile_patterns() -> &'static [(Regex, &'static str)] {
+    static PATTERNS: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        vec![
+            (
+                Regex::new(r"\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(\.\d+)?(Z|[+-]\d{2}:?\d{2})?")
+                    .unwrap(),
+                "<timestamp>",
+            ),
+            (
+                Regex::new(
+                    r"[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}",
+                )
+                .unwrap(),
+                "<uuid>",
+            ),
+            (
+                Regex::new(r"Runtime: [0-9.]+ hours").unwrap(),
+                "Runtime: <n> hours",
+            ),
+            (Regex::new(r"in [0-9.]+m?s\b").unwrap(), "in <duration>"),
+        ]
+    })
+}
+
+fn normalise(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::String(text) => {
+            for (pattern, replacement) in volatile_patterns() {
+                if pattern.is_match(text) {
+                    *text = pattern.replace_all(text, *replacement).into_owned();
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(normalise),
+        serde_json::Value::Object(fields) => fields.values_mut().for_each(normalise),
+        _ => {}
+    }
+}
+
+/// Stable key for a request: SHA-256 of its normalised JSON.
+pub fn request_key(request: &ChatCompletionRequest) -> Result<String> {
+    let mut value = serde_json::to_value(request)?;
+    if let Some(fields) = value.as_object_mut() {
+        fields.remove("stream");
+    }
+    normalise(&mut value);
+    digest(&value)
+}
+
+/// Key for an embeddings call; inputs are file chunks, so nothing is masked.
+fn embeddings_key(model: &str, input: &[String]) -> Result<String> {
+    let value = serde_json::json!({ "embeddings": { "model": model, "input": input } });
+    digest(&value)
+}
+
+fn digest(value: &serde_json::Value) -> Result<String> {
+    // serde_json maps are sorted, so this serialisation is canonical
+    let digest = Sha256::digest(serde_json::to_vec(value)?);
+    Ok(digest.iter().map(|b| format!("{:02x}", b)).collect())
+}
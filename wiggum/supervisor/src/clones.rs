@@ -0,0 +1,357 @@
+use crate::tools::SKIPPED_DIRS;
+use crate::verdict::{Finding, Severity};
+use anyhow::Result;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use walkdir::WalkDir;
+
+/// Shortest duplicated token run reported by default.
+pub const DEFAULT_MIN_CLONE_TOKENS: usize = 60;
+
+const CLONE_EXTENSIONS: [&str; 9] = [
+    "js", "mjs", "cjs", "ts", "jsx", "tsx", "vue", "svelte", "rs",
+];
+/// Generated and vendored files are not the agent's duplication.
+const MAX_SOURCE_BYTES: u64 = 256 * 1024;
+/// Occurrences of one window compared pairwise; boilerplate repeated more
+/// often than this is reported through its first occurrences only.
+const MAX_BUCKET: usize = 16;
+
+/// Words kept as they are; every other identifier becomes `$id`, so renamed
+/// copies still match.
+const KEYWORDS: [&str; 62] = [
+    "async",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "from",
+    "function",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "let",
+    "new",
+    "null",
+    "of",
+    "return",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "type",
+    "typeof",
+    "undefined",
+    "var",
+    "void",
+    "while",
+    "yield",
+    "as",
+    "crate",
+    "dyn",
+    "fn",
+    "impl",
+    "loop",
+    "match",
+    "mod",
+    "move",
+    "mut",
+    "pub",
+    "ref",
+    "self",
+    "Self",
+    "struct",
+    "trait",
+    "use",
+];
+
+/// Where one copy of a clone lives.
+#[derive(Debug, Clone)]
+pub struct CloneSpan {
+    /// Path relative to the workspace, with `/` separators
+    pub file: String,
+    pub start_line: u32,
+    pub end_line: u32,
+}
+
+/// Two places sharing the same normalised token sequence.
+#[derive(Debug, Clone)]
+pub struct ClonePair {
+    pub tokens: usize,
+    pub first: CloneSpan,
+    pub second: CloneSpan,
+}
+
+impl ClonePair {
+    pub fn finding(&self) -> Finding {
+        Finding::new(
+            Severity::Major,
+            format!(
+                "{} tokens (lines {}-{}) duplicated at {}:{}-{}; extract the shared code",
+                self.tokens,
+                self.first.start_line,
+                self.first.end_line,
+                self.second.file,
+                self.second.start_line,
+                self.second.end_line
+            ),
+        )
+        .at(self.first.file.clone(), Some(self.first.start_line))
+        .rule("slop:duplication")
+    }
+}
+
+/// Token-based clone detection: identifiers and literals are normalised,
+/// windows of `min_tokens` are hashed, and matching windows are grown into
+/// maximal clone pairs.
+pub struct CloneDetector {
+    min_tokens: usize,
+}
+
+struct SourceFile {
+    path: String,
+    tokens: Vec<u32>,
+    lines: Vec<u32>,
+}
+
+impl CloneDetector {
+    pub fn new(min_tokens: usize) -> Self {
+        Self {
+            min_tokens: min_tokens.max(1),
+        }
+    }
+
+    /// Clone pairs in the workspace's sources, largest first.
+    pub fn detect(&self, root: &Path) -> Result<Vec<ClonePair>> {
+        let mut vocabulary: HashMap<String, u32> = HashMap::new();
+        let mut files = Vec::new();
+
+        let walker = WalkDir::new(root)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|e| !SKIPPED_DIRS.contains(&e.file_name().to_string_lossy().as_ref()));
+
+        for entry in walker
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+        {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy();
+            let wanted = path
+                .extension()
+                .is_some_and(|ext| CLONE_EXTENSIONS.contains(&ext.to_string_lossy().as_ref()));
+            let small = entry
+                .metadata()
+                .map(|m| m.len() <= MAX_SOURCE_BYTES)
+                .unwrap_or(false);
+            if !wanted || !small || name.contains(".min.") {
+                continue;
+            }
+            let Ok(source) = fs::read_to_string(path) else {
+                continue;
+            };
+
+            let (tokens, lines): (Vec<u32>, Vec<u32>) = tokenize(&source)
+                .into_iter()
+                .map(|(token, line)| {
+                    let next = vocabulary.len() as u32;
+                    (*vocabulary.entry(token).or_insert(next), line)
+                })
+                .unzip();
+
+            files.push(SourceFile {
+                path: path
+                    .strip_prefix(root)
+                    .unwrap_or(path)
+                    .to_string_lossy()
+                    .replace('\\', "/"),
+                tokens,
+                lines,
+            });
+        }
+
+        Ok(self.pairs(&files))
+    }
+
+    fn pairs(&self, files: &[SourceFile]) -> Vec<ClonePair> {
+        let window = self.min_tokens;
+        let mut buckets: HashMap<u64, Vec<(usize, usize)>> = HashMap::new();
+
+        for (index, file) in files.iter().enumerate() {
+            for start in 0..file.tokens.len().saturating_sub(window - 1) {
+                let mut hasher = DefaultHasher::new();
+                file.tokens[start..start + window].hash(&mut hasher);
+                let bucket = buckets.entry(hasher.finish()).or_default();
+                if bucket.len() < MAX_BUCKET {
+                    bucket.push((index, start));
+                }
+            }
+        }
+
+        let mut pairs = Vec::new();
+        for bucket in buckets.values().filter(|b| b.len() > 1) {
+            for (i, &(file_a, start_a)) in bucket.iter().enumerate() {
+                for &(file_b, start_b) in &bucket[i + 1..] {
+                    let (a, b) = (&files[file_a], &files[file_b]);
+
+                    // Only report a clone from where it starts
+                    if start_a > 0 && start_b > 0 && a.tokens[start_a - 1] == b.tokens[start_b - 1]
+                    {
+                        continue;
+                    }
+
+                    let mut length = a.tokens[start_a..]
+                        .iter()
+                        .zip(&b.tokens[start_b..])
+                        .take_while(|(x, y)| x == y)
+                        .count();
+                    // A copy may not overlap itself
+                    if file_a == file_b {
+                        length = length.min(start_b.abs_diff(start_a));
+                    }
+                    if length < window {
+                        continue;
+                    }
+
+                    pairs.push(ClonePair {
+                        tokens: length,
+                        first: span(a, start_a, length),
+                        second: span(b, start_b, length),
+                    });
+                }
+            }
+        }
+
+        pairs.sort_by(|x, y| {
+            y.tokens
+                .cmp(&x.tokens)
+                .then_with(|| x.first.file.cmp(&y.first.file))
+                .then_with(|| x.first.start_line.cmp(&y.first.start_line))
+        });
+        pairs
+    }
+}
+
+fn span(file: &SourceFile, start: usize, length: usize) -> CloneSpan {
+    CloneSpan {
+        file: file.path.clone(),
+        start_line: file.lines[start],
+        end_line: file.lines[start + length - 1],
+    }
+}
+
+/// Normalised tokens of a C-family source with the line each starts on.
+/// Comments and whitespace are dropped, identifiers become `$id`, string
+/// and number literals `$str` and `$num`; keywords and punctuation stay.
+fn tokenize(source: &str) -> Vec<(String, u32)> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut line = 1u32;
+    let mut index = 0;
+
+    let is_word = |b: u8| b.is_ascii_alphanumeric() || b == b'_' || b == b'$' || b >= 0x80;
+
+    while index < bytes.len() {
+        let byte = bytes[index];
+        let rest = &bytes[index..];
+
+        if byte == b'\n' {
+            line += 1;
+            index += 1;
+        } else if byte.is_ascii_whitespace() {
+            index += 1;
+        } else if rest.starts_with(b"//") {
+            index += rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len());
+        } else if let Some(close) = [(&b"/*"[..], &b"*/"[..]), (b"<!--", b"-->")]
+            .iter()
+            .find(|(open, _)| rest.starts_with(open))
+            .map(|(_, close)| *close)
+        {
+            let end = find(rest, close)
+                .map(|at| at + close.len())
+                .unwrap_or(rest.len());
+            line += count_lines(&rest[..end]);
+            index += end;
+        } else if matches!(byte, b'"' | b'\'' | b'`') {
+            match string_end(rest, byte) {
+                Some(end) => {
+                    tokens.push(("$str".to_string(), line));
+                    line += count_lines(&rest[..end]);
+                    index += end;
+                }
+                // An unclosed quote is a Rust lifetime or a stray apostrophe
+                None => {
+                    tokens.push(((byte as char).to_string(), line));
+                    index += 1;
+                }
+            }
+        } else if byte.is_ascii_digit() {
+            let end = rest
+                .iter()
+                .position(|&b| !(is_word(b) || b == b'.'))
+                .unwrap_or(rest.len());
+            tokens.push(("$num".to_string(), line));
+            index += end;
+        } else if is_word(byte) {
+            let end = rest.iter().position(|&b| !is_word(b)).unwrap_or(rest.len());
+            let word = String::from_utf8_lossy(&rest[..end]);
+            let token = if KEYWORDS.contains(&word.as_ref()) {
+                word.into_owned()
+            } else {
+                "$id".to_string()
+            };
+            tokens.push((token, line));
+            index += end;
+        } else {
+            tokens.push(((byte as char).to_string(), line));
+            index += 1;
+        }
+    }
+
+    tokens
+}
+
+/// Length of the string literal at the start of `rest`, quotes included.
+/// Only template literals may span lines.
+fn string_end(rest: &[u8], quote: u8) -> Option<usize> {
+    let mut index = 1;
+    while index < rest.len() {
+        match rest[index] {
+            b'\\' => index += 2,
+            b'\n' if quote != b'`' => return None,
+            b if b == quote => return Some(index + 1),
+            _ => index += 1,
+        }
+    }
+    None
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn count_lines(bytes: &[u8]) -> u32 {
+    bytes.iter().filter(|&&b| b == b'\n').count() as u32
+}
@@ -1,7 +1,7 @@
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::path::{Path, PathBuf};
 use std::fs;
-use anyhow::Result;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CostTracker {
@@ -104,4 +104,4 @@ impl CostPressure {
     pub fn get_tracker(&self) -> &CostTracker {
         &self.tracker
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,381 @@
+use crate::module_graph::{package_name, relative_path, Imported, ModuleGraph};
+use crate::tools::SKIPPED_DIRS;
+use crate::verdict::{Finding, Severity};
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use walkdir::WalkDir;
+
+/// Files a package starts from by convention.
+const CONVENTIONAL_ENTRIES: [&str; 6] = [
+    "index",
+    "main",
+    "server",
+    "src/index",
+    "src/main",
+    "src/server",
+];
+
+/// Directories whose files test runners pick up by themselves.
+const TEST_DIRS: [&str; 5] = ["test", "tests", "__tests__", "e2e", "cypress"];
+
+#[derive(Debug, Clone)]
+pub enum DeadCode {
+    /// A module no entry point reaches through its imports
+    UnreferencedFile { path: String },
+    UnusedExport {
+        path: String,
+        line: u32,
+        name: String,
+    },
+    /// A runtime dependency nothing in its package imports or mentions
+    UnusedDependency { manifest: String, name: String },
+}
+
+impl DeadCode {
+    pub fn finding(&self) -> Finding {
+        match self {
+            DeadCode::UnreferencedFile { path } => Finding::new(
+                Severity::Major,
+                "File is not imported from any entry point; delete it or wire it in",
+            )
+            .at(path.clone(), None)
+            .rule("slop:dead-file"),
+            DeadCode::UnusedExport { path, line, name } => Finding::new(
+                Severity::Minor,
+                format!("Export `{}` is not imported anywhere", name),
+            )
+            .at(path.clone(), Some(*line))
+            .rule("slop:unused-export"),
+            DeadCode::UnusedDependency { manifest, name } => Finding::new(
+                Severity::Minor,
+                format!(
+                    "Dependency `{}` is never imported; remove it from package.json",
+                    name
+                ),
+            )
+            .at(manifest.clone(), None)
+            .rule("slop:unused-dependency"),
+        }
+    }
+}
+
+/// Dead code found by walking the import graph from the entry points.
+#[derive(Debug, Clone, Default)]
+pub struct DeadCodeReport {
+    pub entries: Vec<String>,
+    pub items: Vec<DeadCode>,
+}
+
+struct Patterns {
+    script_src: Regex,
+    command_word: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| Patterns {
+        script_src: Regex::new(r#"<script[^>]*\ssrc=["']([^"']+)["']"#).unwrap(),
+        command_word: Regex::new(r"[^\s&|;]+").unwrap(),
+    })
+}
+
+/// A package.json and what it declares.
+struct Manifest {
+    dir: PathBuf,
+    path: String,
+    json: serde_json::Value,
+}
+
+/// Find unreferenced files, unused exports and unused dependencies.
+/// Entry points are the files package.json points at (main, bin, exports
+/// and scripts), scripts loaded by HTML pages, conventional `index`/`main`
+/// files, tool configs and tests.
+pub fn analyze(graph: &ModuleGraph) -> DeadCodeReport {
+    let manifests = manifests(&graph.root);
+    let entries = entry_points(graph, &manifests);
+    let mut items = Vec::new();
+
+    // Without an entry point every file would look dead
+    if !entries.is_empty() {
+        let reachable = reachable(graph, &entries);
+        items.extend(
+            graph
+                .modules
+                .keys()
+                .filter(|path| {
+                    !reachable.contains(*path) && !path.ends_with(".d.ts") && !path.ends_with(".rs")
+                })
+                .map(|path| DeadCode::UnreferencedFile { path: path.clone() }),
+        );
+    }
+
+    items.extend(unused_exports(graph, &entries));
+    items.extend(unused_dependencies(graph, &manifests));
+
+    DeadCodeReport {
+        entries: entries.into_iter().collect(),
+        items,
+    }
+}
+
+impl DeadCodeReport {
+    pub fn summary(&self) -> String {
+        if self.entries.is_empty() && self.items.is_empty() {
+            return "dead code: no entry points found, unreferenced files not checked".to_string();
+        }
+
+        let count = |f: fn(&DeadCode) -> bool| self.items.iter().filter(|i| f(i)).count();
+        format!(
+            "dead code from {} entry points: {} unreferenced files, {} unused exports, {} unused dependencies",
+            self.entries.len(),
+            count(|i| matches!(i, DeadCode::UnreferencedFile { .. })),
+            count(|i| matches!(i, DeadCode::UnusedExport { .. })),
+            count(|i| matches!(i, DeadCode::UnusedDependency { .. })),
+        )
+    }
+
+    pub fn findings(&self) -> Vec<Finding> {
+        self.items.iter().map(DeadCode::finding).collect()
+    }
+}
+
+fn manifests(root: &Path) -> Vec<Manifest> {
+    WalkDir::new(root)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| !SKIPPED_DIRS.contains(&e.file_name().to_string_lossy().as_ref()))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file() && e.file_name() == "package.json")
+        .filter_map(|e| {
+            let json = serde_json::from_str(&fs::read_to_string(e.path()).ok()?).ok()?;
+            let path = relative_path(root, e.path());
+            let dir = Path::new(&path)
+                .parent()
+                .unwrap_or(Path::new(""))
+                .to_path_buf();
+            Some(Manifest { dir, path, json })
+        })
+        .collect()
+}
+
+fn entry_points(graph: &ModuleGraph, manifests: &[Manifest]) -> BTreeSet<String> {
+    let mut entries = BTreeSet::new();
+
+    for manifest in manifests {
+        let mut declared = Vec::new();
+        for field in ["main", "module", "browser", "bin", "exports"] {
+            strings(&manifest.json[field], &mut declared);
+        }
+        for script in manifest.json["scripts"]
+            .as_object()
+            .into_iter()
+            .flat_map(|s| s.values())
+        {
+            let command = script.as_str().unwrap_or_default();
+            declared.extend(
+                patterns()
+                    .command_word
+                    .find_iter(command)
+                    .map(|w| w.as_str().to_string()),
+            );
+        }
+        declared.extend(CONVENTIONAL_ENTRIES.iter().map(|e| e.to_string()));
+
+        entries.extend(
+            declared
+                .iter()
+                .filter_map(|path| graph.resolve_path(&manifest.dir.join(path))),
+        );
+    }
+
+    for (path, module) in &graph.modules {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        let in_test_dir = path.split('/').any(|segment| TEST_DIRS.contains(&segment));
+        let top_level_of_package = manifests
+            .iter()
+            .any(|m| Path::new(path).parent() == Some(m.dir.as_path()));
+        let config = name.contains(".config.") && top_level_of_package;
+        if in_test_dir || name.contains(".test.") || name.contains(".spec.") || config {
+            entries.insert(module.path.clone());
+        }
+    }
+
+    entries.extend(html_scripts(graph));
+    entries.retain(|path| graph.modules.contains_key(path));
+    entries
+}
+
+/// Every string inside a package.json field, e.g. the paths of an
+/// `exports` map.
+fn strings(value: &serde_json::Value, out: &mut Vec<String>) {
+    match value {
+        serde_json::Value::String(s) => out.push(s.clone()),
+        serde_json::Value::Array(items) => items.iter().for_each(|v| strings(v, out)),
+        serde_json::Value::Object(map) => map.values().for_each(|v| strings(v, out)),
+        _ => {}
+    }
+}
+
+/// Modules loaded by `<script src>` in the workspace's HTML pages.
+fn html_scripts(graph: &ModuleGraph) -> Vec<String> {
+    let walker = WalkDir::new(&graph.root)
+        .into_iter()
+        .filter_entry(|e| !SKIPPED_DIRS.contains(&e.file_name().to_string_lossy().as_ref()));
+
+    let mut found = Vec::new();
+    for entry in walker
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "html"))
+    {
+        let Ok(html) = fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        let page = relative_path(&graph.root, entry.path());
+        let page_dir = Path::new(&page)
+            .parent()
+            .unwrap_or(Path::new(""))
+            .to_path_buf();
+        let package_dir = graph.package_dir(&page);
+
+        for captures in patterns().script_src.captures_iter(&html) {
+            let src = &captures[1];
+            if src.contains("://") {
+                continue;
+            }
+            // Dev servers serve `/src/main.js` from the package, static
+            // servers from the page's own directory
+            let bases: Vec<&Path> = match src.strip_prefix('/') {
+                Some(_) => vec![&page_dir, &package_dir],
+                None => vec![&page_dir],
+            };
+            let resolved = bases
+                .iter()
+                .find_map(|base| graph.resolve_path(&base.join(src.trim_start_matches('/'))));
+            found.extend(resolved);
+        }
+    }
+
+    found
+}
+
+fn reachable(graph: &ModuleGraph, entries: &BTreeSet<String>) -> BTreeSet<String> {
+    let mut seen: BTreeSet<String> = entries.clone();
+    let mut queue: VecDeque<String> = entries.iter().cloned().collect();
+
+    while let Some(path) = queue.pop_front() {
+        let Some(module) = graph.modules.get(&path) else {
+            continue;
+        };
+        for target in module.imports.iter().filter_map(|i| i.target.as_ref()) {
+            if seen.insert(target.clone()) {
+                queue.push_back(target.clone());
+            }
+        }
+    }
+    seen
+}
+
+/// Exports of non-entry modules that no import asks for. Entry points'
+/// exports are their public interface, and a component's default export
+/// is what its framework loads.
+fn unused_exports(graph: &ModuleGraph, entries: &BTreeSet<String>) -> Vec<DeadCode> {
+    let mut used: BTreeMap<&str, Option<BTreeSet<&str>>> = BTreeMap::new();
+    for import in graph.modules.values().flat_map(|m| &m.imports) {
+        let Some(target) = import.target.as_deref() else {
+            continue;
+        };
+        let names = used.entry(target).or_insert_with(|| Some(BTreeSet::new()));
+        match (&import.imported, names) {
+            (Imported::Names(imported), Some(names)) => {
+                names.extend(imported.iter().map(String::as_str))
+            }
+            (Imported::Everything, names) => *names = None,
+            (Imported::Names(_), None) => {}
+        }
+    }
+
+    let mut unused = Vec::new();
+    for module in graph
+        .modules
+        .values()
+        .filter(|m| !entries.contains(&m.path) && !m.path.ends_with(".vue"))
+    {
+        // Files nobody imports are reported as a whole, and a namespace
+        // import may use any export
+        let Some(Some(names)) = used.get(module.path.as_str()) else {
+            continue;
+        };
+        for export in module
+            .exports
+            .iter()
+            .filter(|e| !names.contains(e.name.as_str()))
+        {
+            unused.push(DeadCode::UnusedExport {
+                path: module.path.clone(),
+                line: export.line,
+                name: export.name.clone(),
+            });
+        }
+    }
+    unused
+}
+
+/// `dependencies` that no module of the package imports or names, and no
+/// script runs. Dev dependencies are tooling and are left alone.
+fn unused_dependencies(graph: &ModuleGraph, manifests: &[Manifest]) -> Vec<DeadCode> {
+    let mut unused = Vec::new();
+
+    for manifest in manifests {
+        let Some(dependencies) = manifest.json["dependencies"].as_object() else {
+            continue;
+        };
+        let modules: Vec<&str> = graph
+            .modules
+            .keys()
+            .filter(|path| graph.package_dir(path) == manifest.dir)
+            .map(String::as_str)
+            .collect();
+
+        let mut used: BTreeSet<String> = modules
+            .iter()
+            .flat_map(|path| &graph.modules[*path].imports)
+            .filter_map(|import| package_name(&import.specifier).map(str::to_string))
+            .collect();
+        // Components and JSX use their framework without importing it
+        if modules.iter().any(|p| p.ends_with(".vue")) {
+            used.insert("vue".to_string());
+        }
+        if modules
+            .iter()
+            .any(|p| p.ends_with(".jsx") || p.ends_with(".tsx"))
+        {
+            used.insert("react".to_string());
+        }
+
+        let scripts = manifest.json["scripts"].to_string();
+        let sources: Vec<String> = modules
+            .iter()
+            .filter_map(|path| fs::read_to_string(graph.root.join(path)).ok())
+            .collect();
+
+        for name in dependencies.keys() {
+            let bare = name.rsplit('/').next().unwrap_or(name);
+            // Drivers and plugins are often named in config rather than imported
+            let named = sources.iter().any(|s| {
+                s.contains(&format!("'{}'", name)) || s.contains(&format!("\"{}\"", name))
+            });
+            if used.contains(name) || name.starts_with("@types/") || named || scripts.contains(bare)
+            {
+                continue;
+            }
+            unused.push(DeadCode::UnusedDependency {
+                manifest: manifest.path.clone(),
+                name: name.clone(),
+            });
+        }
+    }
+    unused
+}
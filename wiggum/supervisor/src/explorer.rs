@@ -0,0 +1,328 @@
+use crate::browser::{AxSnapshot, Page};
+use crate::llm::{ToolCall, ToolDefinition};
+use crate::tools::{truncate, ToolSet};
+use crate::verdict::{Finding, Severity};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Mutex;
+use tracing::{info, warn};
+
+/// Something the explorer ran into, with the steps that lead there.
+#[derive(Debug, Clone)]
+pub struct ExplorationIssue {
+    pub kind: IssueKind,
+    pub description: String,
+    pub steps: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    /// Nothing on the page lets the user go on with what they were doing
+    DeadEnd,
+    /// Uncaught errors, blank pages, failed requests
+    Crash,
+    /// The app works but not the way the intent says it should
+    IntentMismatch,
+}
+
+impl IssueKind {
+    fn severity(self) -> Severity {
+        match self {
+            IssueKind::Crash => Severity::Critical,
+            IssueKind::DeadEnd | IssueKind::IntentMismatch => Severity::Major,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            IssueKind::DeadEnd => "dead_end",
+            IssueKind::Crash => "crash",
+            IssueKind::IntentMismatch => "intent_mismatch",
+        }
+    }
+}
+
+/// Browser tools for exploratory testing: the model sees the page's
+/// accessibility tree and acts on numbered elements. Every action is
+/// logged so each issue carries the steps that reproduce it.
+pub struct BrowserTools {
+    page: Page,
+    base_url: String,
+    session: Mutex<Session>,
+}
+
+#[derive(Default)]
+struct Session {
+    snapshot: AxSnapshot,
+    steps: Vec<String>,
+    issues: Vec<ExplorationIssue>,
+}
+
+#[derive(Deserialize)]
+struct NavigateArgs {
+    path: String,
+}
+
+#[derive(Deserialize)]
+struct ClickArgs {
+    #[serde(rename = "ref")]
+    reference: usize,
+}
+
+#[derive(Deserialize)]
+struct TypeArgs {
+    #[serde(rename = "ref")]
+    reference: usize,
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct PressArgs {
+    key: String,
+}
+
+#[derive(Deserialize)]
+struct ReportArgs {
+    kind: IssueKind,
+    description: String,
+}
+
+impl BrowserTools {
+    /// Open the app's start page in `page`.
+    pub async fn open(page: Page, base_url: &str) -> Result<Self> {
+        let tools = Self {
+            page,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            session: Mutex::new(Session::default()),
+        };
+        tools.page.navigate(&format!("{}/", tools.base_url)).await?;
+        tools
+            .session
+            .lock()
+            .unwrap()
+            .steps
+            .push("Open /".to_string());
+        Ok(tools)
+    }
+
+    /// The current page as the model sees it, with any errors it raised.
+    pub async fn observe(&self) -> Result<String> {
+        let snapshot = self.page.accessibility_tree().await?;
+        let url = self.page.url().await.unwrap_or_default();
+        let problems = self.page.take_problems();
+
+        let mut out = format!("URL: {}\n{}", url, snapshot.outline);
+        let mut session = self.session.lock().unwrap();
+        session.snapshot = snapshot;
+
+        // Errors are issues in their own right, reproduced by the steps so far
+        for error in &problems.console_errors {
+            out.push_str(&format!("\nCONSOLE ERROR: {}", error));
+            session.record(IssueKind::Crash, format!("Console error: {}", error));
+        }
+        for request in &problems.failed_requests {
+            out.push_str(&format!("\nFAILED REQUEST: {}", request));
+            session.record(IssueKind::Crash, format!("Request failed: {}", request));
+        }
+        Ok(out)
+    }
+
+    pub fn issues(&self) -> Vec<ExplorationIssue> {
+        self.session.lock().unwrap().issues.clone()
+    }
+
+    /// Issues as findings, each listing its reproduction steps.
+    pub fn findings(&self) -> Vec<Finding> {
+        self.issues()
+            .iter()
+            .map(|issue| {
+                let steps = issue
+                    .steps
+                    .iter()
+                    .enumerate()
+                    .map(|(index, step)| format!("{}. {}", index + 1, step))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                Finding::new(
+                    issue.kind.severity(),
+                    format!("{}\nSteps to reproduce:\n{}", issue.description, steps),
+                )
+                .rule(format!("explore:{}", issue.kind.name()))
+            })
+            .collect()
+    }
+
+    async fn navigate(&self, arguments: &str) -> Result<String> {
+        let args: NavigateArgs = serde_json::from_str(arguments)?;
+        if args.path.contains("://") {
+            return Err(anyhow!("Only paths within the app can be opened"));
+        }
+
+        self.log(format!("Open {}", args.path));
+        self.page
+            .navigate(&format!(
+                "{}/{}",
+                self.base_url,
+                args.path.trim_start_matches('/')
+            ))
+            .await?;
+        self.observe().await
+    }
+
+    async fn click(&self, arguments: &str) -> Result<String> {
+        let args: ClickArgs = serde_json::from_str(arguments)?;
+        let (node, label) = self.element(args.reference)?;
+
+        self.log(format!("Click {}", label));
+        self.page.click_node(node).await?;
+        self.settle().await;
+        self.observe().await
+    }
+
+    async fn type_text(&self, arguments: &str) -> Result<String> {
+        let args: TypeArgs = serde_json::from_str(arguments)?;
+        let (node, label) = self.element(args.reference)?;
+
+        self.log(format!("Type \"{}\" into {}", args.text, label));
+        self.page.fill_node(node, &args.text).await?;
+        self.observe().await
+    }
+
+    async fn press(&self, arguments: &str) -> Result<String> {
+        let args: PressArgs = serde_json::from_str(arguments)?;
+
+        self.log(format!("Press {}", args.key));
+        self.page.press(&args.key).await?;
+        self.settle().await;
+        self.observe().await
+    }
+
+    fn report(&self, arguments: &str) -> Result<String> {
+        let args: ReportArgs = serde_json::from_str(arguments)?;
+        info!(
+            "Explorer reported {}: {}",
+            args.kind.name(),
+            args.description
+        );
+        self.session
+            .lock()
+            .unwrap()
+            .record(args.kind, args.description);
+        Ok("Recorded with the steps taken so far.".to_string())
+    }
+
+    /// Backend node and label of `[reference]` in the latest snapshot.
+    fn element(&self, reference: usize) -> Result<(i64, String)> {
+        let session = self.session.lock().unwrap();
+        let node = session
+            .snapshot
+            .node(reference)
+            .ok_or_else(|| anyhow!("No element [{}] on the current page", reference))?;
+        let label = session
+            .snapshot
+            .label(reference)
+            .unwrap_or_default()
+            .to_string();
+        Ok((node, label))
+    }
+
+    fn log(&self, step: String) {
+        self.session.lock().unwrap().steps.push(step);
+    }
+
+    /// Give the app a moment to react to input before looking again.
+    async fn settle(&self) {
+        tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+    }
+}
+
+impl Session {
+    fn record(&mut self, kind: IssueKind, description: String) {
+        self.issues.push(ExplorationIssue {
+            kind,
+            description,
+            steps: self.steps.clone(),
+        });
+    }
+}
+
+#[async_trait]
+impl ToolSet for BrowserTools {
+    fn definitions(&self) -> Vec<ToolDefinition> {
+        let reference = json!({ "type": "integer", "description": "Number of the element in the latest page outline, e.g. 3 for [3]" });
+        vec![
+            ToolDefinition::function(
+                "navigate",
+                "Open a page of the app by path, as if typed into the address bar",
+                json!({
+                    "type": "object",
+                    "properties": { "path": { "type": "string", "description": "Path such as / or /settings" } },
+                    "required": ["path"]
+                }),
+            ),
+            ToolDefinition::function(
+                "click",
+                "Click an element of the current page",
+                json!({ "type": "object", "properties": { "ref": reference }, "required": ["ref"] }),
+            ),
+            ToolDefinition::function(
+                "type",
+                "Replace the contents of a text field with the given text",
+                json!({
+                    "type": "object",
+                    "properties": { "ref": reference, "text": { "type": "string" } },
+                    "required": ["ref", "text"]
+                }),
+            ),
+            ToolDefinition::function(
+                "press",
+                "Press a key in the focused element, e.g. Enter, Tab or Escape",
+                json!({
+                    "type": "object",
+                    "properties": { "key": { "type": "string" } },
+                    "required": ["key"]
+                }),
+            ),
+            ToolDefinition::function(
+                "report_issue",
+                "Record a problem; the steps taken so far are attached to reproduce it",
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "kind": { "type": "string", "enum": ["dead_end", "crash", "intent_mismatch"] },
+                        "description": { "type": "string", "description": "What you expected and what happened instead" }
+                    },
+                    "required": ["kind", "description"]
+                }),
+            ),
+        ]
+    }
+
+    async fn execute(&self, call: &ToolCall) -> String {
+        info!(
+            "Browser tool call: {}({})",
+            call.function.name, call.function.arguments
+        );
+
+        let arguments = call.function.arguments.as_str();
+        let result = match call.function.name.as_str() {
+            "navigate" => self.navigate(arguments).await,
+            "click" => self.click(arguments).await,
+            "type" => self.type_text(arguments).await,
+            "press" => self.press(arguments).await,
+            "report_issue" => self.report(arguments),
+            other => Err(anyhow!("Unknown tool: {}", other)),
+        };
+
+        match result {
+            Ok(output) => truncate(output),
+            Err(e) => {
+                warn!("Browser tool {} failed: {}", call.function.name, e);
+                format!("ERROR: {}", e)
+            }
+        }
+    }
+}
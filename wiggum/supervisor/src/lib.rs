@@ -0,0 +1,31 @@
+pub mod acceptance;
+pub mod agents;
+pub mod api_flow;
+pub mod app;
+pub mod architecture;
+pub mod browser;
+pub mod budget;
+pub mod cassette;
+pub mod clones;
+pub mod cost;
+pub mod dead_code;
+pub mod explorer;
+pub mod linters;
+pub mod llm;
+pub mod log_analysis;
+pub mod metrics;
+pub mod mock_server;
+pub mod models;
+pub mod module_graph;
+pub mod provider;
+pub mod repo_map;
+pub mod retry;
+pub mod state;
+pub mod supervisor;
+pub mod test_runner;
+pub mod tools;
+pub mod traceability;
+pub mod vector_index;
+pub mod verdict;
+
+pub use supervisor::{Supervisor, SupervisorConfig};
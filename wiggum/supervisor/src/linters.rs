@@ -0,0 +1,489 @@
+use crate::tools::SKIPPED_DIRS;
+use crate::verdict::{Finding, Severity};
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::process::Command;
+use tracing::{info, warn};
+use walkdir::WalkDir;
+
+/// Default bound on one linter run.
+pub const LINT_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Lines of output kept when a linter fails without diagnostics.
+const OUTPUT_TAIL_LINES: usize = 20;
+
+const ESLINT_CONFIGS: [&str; 9] = [
+    "eslint.config.js",
+    "eslint.config.mjs",
+    "eslint.config.cjs",
+    "eslint.config.ts",
+    ".eslintrc",
+    ".eslintrc.js",
+    ".eslintrc.cjs",
+    ".eslintrc.json",
+    ".eslintrc.yml",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinterKind {
+    Eslint,
+    Biome,
+    Tsc,
+    Clippy,
+}
+
+impl LinterKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            LinterKind::Eslint => "eslint",
+            LinterKind::Biome => "biome",
+            LinterKind::Tsc => "tsc",
+            LinterKind::Clippy => "clippy",
+        }
+    }
+
+    /// The package's local binary, for the node tools.
+    fn node_binary(self, dir: &Path) -> Option<PathBuf> {
+        let name = match self {
+            LinterKind::Eslint => "eslint",
+            LinterKind::Biome => "biome",
+            LinterKind::Tsc => "tsc",
+            LinterKind::Clippy => return None,
+        };
+        Some(dir.join("node_modules").join(".bin").join(name))
+    }
+
+    /// Command printing machine-readable diagnostics on stdout.
+    fn command(self, dir: &Path) -> Command {
+        let (program, args): (PathBuf, &[&str]) = match self {
+            LinterKind::Eslint => (
+                self.node_binary(dir).unwrap_or_default(),
+                &[".", "--format", "json"],
+            ),
+            LinterKind::Biome => (
+                self.node_binary(dir).unwrap_or_default(),
+                &["lint", "--reporter=github", "."],
+            ),
+            LinterKind::Tsc => (
+                self.node_binary(dir).unwrap_or_default(),
+                &["--noEmit", "--pretty", "false"],
+            ),
+            LinterKind::Clippy => (
+                PathBuf::from("cargo"),
+                &["clippy", "--quiet", "--message-format=json"],
+            ),
+        };
+
+        let mut command = Command::new(program);
+        command.args(args);
+        command
+    }
+}
+
+/// A linter that applies to part of the workspace.
+#[derive(Debug, Clone)]
+pub struct Linter {
+    pub kind: LinterKind,
+    pub dir: PathBuf,
+}
+
+/// One diagnostic, with its file relative to the workspace.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub file: String,
+    pub line: Option<u32>,
+    pub rule: Option<String>,
+    pub message: String,
+    pub severity: Severity,
+}
+
+/// Outcome of one linter run.
+#[derive(Debug, Clone)]
+pub struct LintRun {
+    pub kind: LinterKind,
+    /// Directory linted, relative to the workspace
+    pub dir: String,
+    pub diagnostics: Vec<Diagnostic>,
+    /// Why the linter did not run, when it is not installed
+    pub skipped: Option<String>,
+    /// Output of a run that failed without producing diagnostics
+    pub error: Option<String>,
+}
+
+/// Find the linters a workspace is set up for: eslint and biome from
+/// their dependency or config file, tsc from a tsconfig.json, clippy from
+/// a Cargo crate.
+pub fn detect(workspace: &Path) -> Vec<Linter> {
+    let mut linters = Vec::new();
+
+    let walker = WalkDir::new(workspace)
+        .max_depth(3)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| !SKIPPED_DIRS.contains(&e.file_name().to_string_lossy().as_ref()));
+
+    for entry in walker
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        let Some(dir) = entry.path().parent() else {
+            continue;
+        };
+        let kinds = match entry.file_name().to_string_lossy().as_ref() {
+            "package.json" => fs::read_to_string(entry.path())
+                .map(|c| node_linters(&c, dir))
+                .unwrap_or_default(),
+            "Cargo.toml" => vec![LinterKind::Clippy],
+            _ => Vec::new(),
+        };
+
+        for kind in kinds {
+            let already = linters
+                .iter()
+                .any(|l: &Linter| l.kind == kind && dir.starts_with(&l.dir));
+            if !already {
+                linters.push(Linter {
+                    kind,
+                    dir: dir.to_path_buf(),
+                });
+            }
+        }
+    }
+
+    linters
+}
+
+fn node_linters(package_json: &str, dir: &Path) -> Vec<LinterKind> {
+    let Ok(package) = serde_json::from_str::<serde_json::Value>(package_json) else {
+        return Vec::new();
+    };
+    let depends_on = |name: &str| {
+        ["dependencies", "devDependencies"]
+            .iter()
+            .any(|section| package.get(section).and_then(|d| d.get(name)).is_some())
+    };
+
+    let mut kinds = Vec::new();
+    if depends_on("eslint") || ESLINT_CONFIGS.iter().any(|c| dir.join(c).exists()) {
+        kinds.push(LinterKind::Eslint);
+    }
+    if depends_on("@biomejs/biome")
+        || dir.join("biome.json").exists()
+        || dir.join("biome.jsonc").exists()
+    {
+        kinds.push(LinterKind::Biome);
+    }
+    if dir.join("tsconfig.json").exists() {
+        kinds.push(LinterKind::Tsc);
+    }
+    kinds
+}
+
+/// Detect and run every linter in the workspace. Linters that are not
+/// installed are reported as skipped rather than as failures.
+pub async fn run_all(workspace: &Path, timeout: Duration) -> Vec<LintRun> {
+    let mut runs = Vec::new();
+    for linter in detect(workspace) {
+        runs.push(linter.run(workspace, timeout).await);
+    }
+    runs
+}
+
+impl Linter {
+    pub async fn run(&self, workspace: &Path, timeout: Duration) -> LintRun {
+        let dir = self
+            .dir
+            .strip_prefix(workspace)
+            .unwrap_or(&self.dir)
+            .display()
+            .to_string();
+        let mut run = LintRun {
+            kind: self.kind,
+            dir: dir.clone(),
+            diagnostics: Vec::new(),
+            skipped: None,
+            error: None,
+        };
+
+        // npx would try to download a missing tool; use only what is installed
+        if let Some(binary) = self.kind.node_binary(&self.dir).filter(|b| !b.exists()) {
+            run.skipped = Some(format!(
+                "{} is not installed",
+                binary.strip_prefix(&self.dir).unwrap_or(&binary).display()
+            ));
+            return run;
+        }
+
+        info!(
+            "Running {} in {}",
+            self.kind.name(),
+            if dir.is_empty() { "." } else { &dir }
+        );
+        let child = self
+            .kind
+            .command(&self.dir)
+            .current_dir(&self.dir)
+            .env("CI", "1")
+            .stdin(Stdio::null())
+            .kill_on_drop(true)
+            .output();
+
+        let output = match tokio::time::timeout(timeout, child).await {
+            Ok(Ok(output)) => output,
+            Ok(Err(e)) if e.kind() == ErrorKind::NotFound => {
+                run.skipped = Some(format!("{} is not available", self.kind.name()));
+                return run;
+            }
+            Ok(Err(e)) => {
+                run.error = Some(format!("could not start: {}", e));
+                return run;
+            }
+            Err(_) => {
+                run.error = Some(format!("did not finish within {}s", timeout.as_secs()));
+                return run;
+            }
+        };
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        if self.kind == LinterKind::Clippy && stderr.contains("no such command: `clippy`") {
+            run.skipped = Some("cargo clippy is not installed".to_string());
+            return run;
+        }
+
+        let parsed = match self.kind {
+            LinterKind::Eslint => parse_eslint_json(&stdout, &self.dir),
+            LinterKind::Biome => Ok(parse_github_annotations(&stdout)),
+            LinterKind::Tsc => Ok(parse_tsc(&stdout)),
+            LinterKind::Clippy => Ok(parse_cargo_json(&stdout)),
+        };
+
+        match parsed {
+            Ok(diagnostics) => {
+                run.diagnostics = diagnostics
+                    .into_iter()
+                    .map(|mut d| {
+                        d.file = Path::new(&dir).join(&d.file).display().to_string();
+                        d
+                    })
+                    .collect();
+            }
+            Err(e) => warn!("Could not parse {} output: {}", self.kind.name(), e),
+        }
+
+        if run.diagnostics.is_empty() && !output.status.success() {
+            run.error = Some(tail(&format!("{}\n{}", stdout, stderr), OUTPUT_TAIL_LINES));
+        }
+        run
+    }
+}
+
+impl LintRun {
+    pub fn summary(&self) -> String {
+        let location = if self.dir.is_empty() {
+            ".".to_string()
+        } else {
+            self.dir.clone()
+        };
+        if let Some(reason) = &self.skipped {
+            return format!("{} in {}: skipped, {}", self.kind.name(), location, reason);
+        }
+        if self.error.is_some() {
+            return format!("{} in {}: failed to run", self.kind.name(), location);
+        }
+
+        let errors = self
+            .diagnostics
+            .iter()
+            .filter(|d| d.severity >= Severity::Major)
+            .count();
+        format!(
+            "{} in {}: {} errors, {} warnings",
+            self.kind.name(),
+            location,
+            errors,
+            self.diagnostics.len() - errors
+        )
+    }
+
+    /// One finding per diagnostic, or one for a linter that crashed.
+    pub fn findings(&self) -> Vec<Finding> {
+        if let Some(error) = &self.error {
+            let message = format!("{} could not lint the code:\n{}", self.kind.name(), error);
+            return vec![Finding::new(Severity::Major, message).rule(self.kind.name())];
+        }
+
+        self.diagnostics
+            .iter()
+            .map(|d| {
+                let rule = match &d.rule {
+                    Some(rule) => format!(
+                        "{}:{}",
+                        self.kind.name(),
+                        rule.trim_start_matches("clippy::")
+                    ),
+                    None => self.kind.name().to_string(),
+                };
+                Finding::new(d.severity, d.message.clone())
+                    .at(d.file.clone(), d.line)
+                    .rule(rule)
+            })
+            .collect()
+    }
+}
+
+fn tail(text: &str, lines: usize) -> String {
+    let all: Vec<&str> = text.trim_end().lines().collect();
+    all[all.len().saturating_sub(lines)..].join("\n")
+}
+
+/// `eslint --format json`: one entry per file with its messages.
+pub fn parse_eslint_json(json: &str, dir: &Path) -> Result<Vec<Diagnostic>> {
+    let files: Vec<serde_json::Value> = serde_json::from_str(json.trim())?;
+    let mut diagnostics = Vec::new();
+
+    for file in &files {
+        let path = file["filePath"]
+            .as_str()
+            .ok_or_else(|| anyhow!("eslint result without filePath"))?;
+        let path = Path::new(path)
+            .strip_prefix(dir)
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|_| PathBuf::from(path));
+
+        for message in file["messages"].as_array().into_iter().flatten() {
+            diagnostics.push(Diagnostic {
+                file: path.display().to_string(),
+                line: message["line"].as_u64().map(|l| l as u32),
+                rule: message["ruleId"].as_str().map(str::to_string),
+                message: message["message"].as_str().unwrap_or_default().to_string(),
+                severity: if message["severity"].as_u64() == Some(2) {
+                    Severity::Major
+                } else {
+                    Severity::Minor
+                },
+            });
+        }
+    }
+
+    Ok(diagnostics)
+}
+
+/// GitHub workflow annotations, as printed by `biome lint --reporter=github`:
+/// `::error title=lint/x,file=src/a.js,line=3,col=7::message`.
+pub fn parse_github_annotations(output: &str) -> Vec<Diagnostic> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let rest = line.strip_prefix("::")?;
+            let (level, rest) = rest.split_once(' ')?;
+            let (properties, message) = rest.split_once("::")?;
+
+            let property = |name: &str| {
+                properties
+                    .split(',')
+                    .find_map(|p| p.strip_prefix(name)?.strip_prefix('='))
+                    .map(str::to_string)
+            };
+
+            Some(Diagnostic {
+                file: property("file")?,
+                line: property("line").and_then(|l| l.parse().ok()),
+                rule: property("title"),
+                message: message.replace("%0A", "\n").replace("%25", "%"),
+                severity: if level == "error" {
+                    Severity::Major
+                } else {
+                    Severity::Minor
+                },
+            })
+        })
+        .collect()
+}
+
+/// `tsc --pretty false`: `src/a.ts(3,7): error TS2322: message`, with
+/// indented continuation lines.
+pub fn parse_tsc(output: &str) -> Vec<Diagnostic> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = PATTERN.get_or_init(|| {
+        Regex::new(r"^(.+?)\((\d+),\d+\): (error|warning) (TS\d+): (.*)$").unwrap()
+    });
+
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    for line in output.lines() {
+        if let Some(captures) = pattern.captures(line) {
+            diagnostics.push(Diagnostic {
+                file: captures[1].to_string(),
+                line: captures[2].parse().ok(),
+                rule: Some(captures[4].to_string()),
+                message: captures[5].to_string(),
+                severity: if &captures[3] == "error" {
+                    Severity::Major
+                } else {
+                    Severity::Minor
+                },
+            });
+        } else if line.starts_with(' ') {
+            if let Some(last) = diagnostics.last_mut() {
+                last.message.push('\n');
+                last.message.push_str(line.trim());
+            }
+        }
+    }
+    diagnostics
+}
+
+/// `cargo --message-format=json`: compiler messages at their primary span.
+/// Messages repeated across targets are reported once.
+pub fn parse_cargo_json(output: &str) -> Vec<Diagnostic> {
+    let mut seen = BTreeSet::new();
+    let mut diagnostics = Vec::new();
+
+    for line in output.lines() {
+        let Ok(event) = serde_json::from_str::<serde_json::Value>(line) else {
+            continue;
+        };
+        if event["reason"] != "compiler-message" {
+            continue;
+        }
+
+        let message = &event["message"];
+        let severity = match message["level"].as_str() {
+            Some("error") => Severity::Major,
+            Some("warning") => Severity::Minor,
+            _ => continue,
+        };
+        // Summaries like "aborting due to 2 previous errors" point nowhere
+        let Some(span) = message["spans"]
+            .as_array()
+            .and_then(|s| s.iter().find(|s| s["is_primary"] == true))
+        else {
+            continue;
+        };
+
+        let diagnostic = Diagnostic {
+            file: span["file_name"].as_str().unwrap_or_default().to_string(),
+            line: span["line_start"].as_u64().map(|l| l as u32),
+            rule: message["code"]["code"].as_str().map(str::to_string),
+            message: message["message"].as_str().unwrap_or_default().to_string(),
+            severity,
+        };
+        if seen.insert((
+            diagnostic.file.clone(),
+            diagnostic.line,
+            diagnostic.message.clone(),
+        )) {
+            diagnostics.push(diagnostic);
+        }
+    }
+
+    diagnostics
+}
@@ -1,91 +1,896 @@
+use crate::budget::{fit_messages, tools_tokens, DEFAULT_CONTEXT_LENGTH};
+use crate::cassette::{Cassette, CassetteMode};
+use crate::provider::{LlmProvider, StreamEvent, StreamFormat};
+use crate::retry::{is_retryable, BreakerState, CircuitBreaker, RetryPolicy};
 use anyhow::Result;
-use reqwest::Client;
+use reqwest::{Client, RequestBuilder};
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tracing::{debug, info, warn};
+
+/// Non-streaming requests wait for the whole body, so allow long tasks.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
 
 #[derive(Debug, Clone)]
 pub struct LlmClient {
     client: Client,
-    base_url: String,
+    provider: Arc<dyn LlmProvider>,
+    model: String,
+    /// Cleared the first time the server rejects `response_format`
+    structured_output: Arc<AtomicBool>,
+    retry: RetryPolicy,
+    breaker: Arc<CircuitBreaker>,
+    cassette: Option<Arc<Cassette>>,
+    /// Context windows by model: configured ones, then whatever the server reported
+    context_lengths: Arc<Mutex<HashMap<String, usize>>>,
+    /// Model for `/v1/embeddings`; retrieval is off without one
+    embedding_model: Option<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum LlmError {
+    #[error("LLM request failed: {status} - {body}")]
+    Status { status: u16, body: String },
+    #[error("LLM stream timed out: {0}")]
+    Timeout(String),
+    #[error("LLM backend unavailable (circuit breaker open)")]
+    BackendUnavailable,
+    #[error("No recorded LLM reply for request {key} (first user message: {prompt:?})")]
+    CassetteMiss { key: String, prompt: String },
 }
 
+/// Request in OpenAI chat format; providers with another wire format
+/// translate it.
 #[derive(Debug, Serialize)]
-struct ChatCompletionRequest {
-    model: String,
-    messages: Vec<ChatMessage>,
-    temperature: f32,
-    max_tokens: Option<u32>,
+pub struct ChatCompletionRequest {
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    #[serde(flatten)]
+    pub params: SamplingParams,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<ToolDefinition>>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<serde_json::Value>,
+}
+
+/// Sampling settings sent with every request of a conversation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SamplingParams {
+    pub temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    pub max_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+impl Default for SamplingParams {
+    fn default() -> Self {
+        Self {
+            temperature: 0.1, // Low temperature for deterministic coding tasks
+            top_p: None,
+            max_tokens: Some(4096),
+            stop: Vec::new(),
+            seed: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: String,
+    #[serde(default)]
+    pub content: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<ToolCall>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+}
+
+impl ChatMessage {
+    fn text(role: &str, content: &str) -> Self {
+        Self {
+            role: role.to_string(),
+            content: Some(content.to_string()),
+            tool_calls: None,
+            tool_call_id: None,
+        }
+    }
+
+    pub fn system(content: &str) -> Self {
+        Self::text("system", content)
+    }
+
+    pub fn user(content: &str) -> Self {
+        Self::text("user", content)
+    }
+
+    pub fn assistant(content: &str) -> Self {
+        Self::text("assistant", content)
+    }
+
+    pub fn tool_result(tool_call_id: &str, content: String) -> Self {
+        Self {
+            role: "tool".to_string(),
+            content: Some(content),
+            tool_calls: None,
+            tool_call_id: Some(tool_call_id.to_string()),
+        }
+    }
+}
+
+/// A message history plus the sampling settings used to continue it.
+/// Agents keep extending the same conversation across turns, so follow-up
+/// questions see everything the model said and every tool result it got.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Conversation {
+    pub messages: Vec<ChatMessage>,
+    pub params: SamplingParams,
+}
+
+impl Conversation {
+    pub fn new(system_prompt: &str) -> Self {
+        Self {
+            messages: vec![ChatMessage::system(system_prompt)],
+            params: SamplingParams::default(),
+        }
+    }
+
+    pub fn with_params(mut self, params: SamplingParams) -> Self {
+        self.params = params;
+        self
+    }
+
+    pub fn push(&mut self, message: ChatMessage) {
+        self.messages.push(message);
+    }
+
+    pub fn push_user(&mut self, content: &str) {
+        self.push(ChatMessage::user(content));
+    }
+
+    /// Text of the most recent assistant message, if any.
+    pub fn last_reply(&self) -> Option<&str> {
+        self.messages
+            .iter()
+            .rev()
+            .find(|m| m.role == "assistant")
+            .and_then(|m| m.content.as_deref())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+/// OpenAI-style function declaration offered to the model.
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolDefinition {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub function: FunctionDefinition,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct ChatMessage {
-    role: String,
-    content: String,
+#[derive(Debug, Clone, Serialize)]
+pub struct FunctionDefinition {
+    pub name: String,
+    pub description: String,
+    pub parameters: serde_json::Value,
+}
+
+impl ToolDefinition {
+    pub fn function(name: &str, description: &str, parameters: serde_json::Value) -> Self {
+        Self {
+            kind: "function".to_string(),
+            function: FunctionDefinition {
+                name: name.to_string(),
+                description: description.to_string(),
+                parameters,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub id: String,
+    #[serde(rename = "type", default = "default_tool_type")]
+    pub kind: String,
+    pub function: FunctionCall,
+}
+
+fn default_tool_type() -> String {
+    "function".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionCall {
+    pub name: String,
+    /// JSON-encoded arguments, exactly as the model produced them
+    pub arguments: String,
+}
+
+/// Incremental piece of an assistant message from a streamed completion.
+#[derive(Debug, Default, Deserialize)]
+pub struct Delta {
+    #[serde(default)]
+    pub content: Option<String>,
+    #[serde(default)]
+    pub tool_calls: Option<Vec<ToolCallDelta>>,
 }
 
 #[derive(Debug, Deserialize)]
-struct ChatCompletionResponse {
-    choices: Vec<Choice>,
+pub struct ToolCallDelta {
+    pub index: usize,
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub function: Option<FunctionCallDelta>,
 }
 
 #[derive(Debug, Deserialize)]
-struct Choice {
-    message: ChatMessage,
+pub struct FunctionCallDelta {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub arguments: Option<String>,
+}
+
+/// Limits applied while reading a streamed completion.
+#[derive(Debug, Clone)]
+pub struct StreamOptions {
+    /// Give up if the server sends nothing for this long
+    pub idle_timeout: Duration,
+    /// Give up if the whole reply takes longer than this
+    pub total_timeout: Duration,
+    /// Stop reading as soon as the reply contains one of these
+    pub stop_markers: Vec<String>,
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        Self {
+            idle_timeout: Duration::from_secs(120),
+            total_timeout: Duration::from_secs(1800),
+            stop_markers: Vec::new(),
+        }
+    }
+}
+
+/// Splits a streamed body into payloads: `data:` fields of server-sent
+/// events, or whole lines for NDJSON.
+#[derive(Debug)]
+struct StreamParser {
+    format: StreamFormat,
+    buffer: Vec<u8>,
+    data: Vec<String>,
+}
+
+impl StreamParser {
+    fn new(format: StreamFormat) -> Self {
+        Self {
+            format,
+            buffer: Vec::new(),
+            data: Vec::new(),
+        }
+    }
+
+    /// Feed raw bytes and return the payloads of every event they complete.
+    fn feed(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(bytes);
+        let mut events = Vec::new();
+
+        while let Some(newline) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=newline).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if self.format == StreamFormat::Ndjson {
+                if !line.trim().is_empty() {
+                    events.push(line.to_string());
+                }
+            } else if line.is_empty() {
+                if !self.data.is_empty() {
+                    events.push(self.data.join("\n"));
+                    self.data.clear();
+                }
+            } else if let Some(data) = line.strip_prefix("data:") {
+                self.data
+                    .push(data.strip_prefix(' ').unwrap_or(data).to_string());
+            }
+            // Comments (":") and other fields (event, id, retry) are not used
+        }
+
+        events
+    }
+}
+
+/// Deltas of a streamed completion, in the order the server sent them.
+pub struct TokenStream {
+    response: reqwest::Response,
+    provider: Arc<dyn LlmProvider>,
+    parser: StreamParser,
+    pending: VecDeque<String>,
+    done: bool,
+}
+
+impl TokenStream {
+    /// Next delta, or `None` once the server signals the end or closes the stream.
+    pub async fn next_delta(&mut self) -> Result<Option<Delta>> {
+        loop {
+            if let Some(data) = self.pending.pop_front() {
+                match self.provider.parse_stream_event(&data)? {
+                    StreamEvent::Delta(delta) => return Ok(Some(delta)),
+                    StreamEvent::Done => {
+                        self.done = true;
+                        self.pending.clear();
+                        return Ok(None);
+                    }
+                    StreamEvent::Skip => continue,
+                }
+            }
+
+            if self.done {
+                return Ok(None);
+            }
+
+            match self.response.chunk().await? {
+                Some(bytes) => self.pending.extend(self.parser.feed(&bytes)),
+                None => self.done = true,
+            }
+        }
+    }
 }
 
 impl LlmClient {
-    pub fn new(base_url: &str) -> Result<Self> {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(300)) // 5 minute timeout for long tasks
-            .build()?;
+    pub fn new(provider: Arc<dyn LlmProvider>, model: &str) -> Result<Self> {
+        let client = Client::builder().build()?;
 
         Ok(Self {
             client,
-            base_url: base_url.to_string(),
+            provider,
+            model: model.to_string(),
+            structured_output: Arc::new(AtomicBool::new(true)),
+            retry: RetryPolicy::default(),
+            breaker: Arc::new(CircuitBreaker::default()),
+            cassette: None,
+            context_lengths: Arc::new(Mutex::new(HashMap::new())),
+            embedding_model: None,
         })
     }
 
-    pub async fn chat_completion(&self, prompt: &str, model: &str) -> Result<String> {
-        let request = ChatCompletionRequest {
+    /// Same backend, retries and breaker, but talking to another model.
+    pub fn with_model(&self, model: &str) -> Self {
+        Self {
             model: model.to_string(),
-            messages: vec![ChatMessage {
-                role: "user".to_string(),
-                content: prompt.to_string(),
-            }],
-            temperature: 0.1, // Low temperature for deterministic coding tasks
-            max_tokens: Some(4096),
+            ..self.clone()
+        }
+    }
+
+    /// Record every exchange to, or serve every exchange from, a cassette.
+    pub fn with_cassette(mut self, cassette: Cassette) -> Self {
+        self.cassette = Some(Arc::new(cassette));
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn with_embedding_model(mut self, model: &str) -> Self {
+        self.embedding_model = Some(model.to_string());
+        self
+    }
+
+    pub fn embedding_model(&self) -> Option<&str> {
+        self.embedding_model.as_deref()
+    }
+
+    /// Use `tokens` as `model`'s context window instead of asking the server.
+    pub fn with_context_length(self, model: &str, tokens: usize) -> Self {
+        self.context_lengths
+            .lock()
+            .unwrap()
+            .insert(model.to_string(), tokens);
+        self
+    }
+
+    /// Context window of `model` in tokens: configured, reported by the
+    /// server, or `DEFAULT_CONTEXT_LENGTH`. Looked up once per model.
+    pub async fn context_length(&self, model: &str) -> usize {
+        if let Some(&tokens) = self.context_lengths.lock().unwrap().get(model) {
+            return tokens;
+        }
+
+        let replaying = self
+            .cassette
+            .as_ref()
+            .is_some_and(|c| c.mode() == CassetteMode::Replay);
+        let reported = if replaying {
+            None
+        } else {
+            self.provider.context_length(&self.client, model).await
         };
+        let tokens = reported.unwrap_or_else(|| {
+            warn!(
+                "Context length of {} unknown, assuming {} tokens",
+                model, DEFAULT_CONTEXT_LENGTH
+            );
+            DEFAULT_CONTEXT_LENGTH
+        });
+        debug!("Context length of {}: {} tokens", model, tokens);
+
+        self.context_lengths
+            .lock()
+            .unwrap()
+            .insert(model.to_string(), tokens);
+        tokens
+    }
+
+    /// Model agents use unless they ask for a specific one
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    pub async fn chat_completion(&self, prompt: &str, model: &str) -> Result<String> {
+        let mut conversation = Conversation::default();
+        conversation.push_user(prompt);
+        let message = self.converse(&mut conversation, &[], model).await?;
+        Ok(message.content.unwrap_or_default())
+    }
+
+    /// Send the conversation, append the assistant's reply to it and return
+    /// that reply, which may contain tool calls instead of content.
+    pub async fn converse(
+        &self,
+        conversation: &mut Conversation,
+        tools: &[ToolDefinition],
+        model: &str,
+    ) -> Result<ChatMessage> {
+        let request = self.request(conversation, tools, model, false).await;
+        let message = self.send(&request).await?;
+        conversation.push(message.clone());
+        Ok(message)
+    }
+
+    /// Like `converse`, but constrains the reply with a `response_format`
+    /// (e.g. a JSON schema). Servers that reject the field are remembered
+    /// and asked again without it; the prompt must then carry the format.
+    pub async fn converse_structured(
+        &self,
+        conversation: &mut Conversation,
+        model: &str,
+        response_format: serde_json::Value,
+    ) -> Result<ChatMessage> {
+        let mut request = self.request(conversation, &[], model, false).await;
+
+        if self.structured_output.load(Ordering::Relaxed) {
+            request.response_format = Some(response_format);
+            match self.send(&request).await {
+                Err(e)
+                    if matches!(
+                        e.downcast_ref::<LlmError>(),
+                        Some(LlmError::Status {
+                            status: 400 | 422,
+                            ..
+                        })
+                    ) =>
+                {
+                    warn!("Server rejected response_format, falling back to prompt-only structure: {}", e);
+                    self.structured_output.store(false, Ordering::Relaxed);
+                    request.response_format = None;
+                }
+                result => {
+                    let message = result?;
+                    conversation.push(message.clone());
+                    return Ok(message);
+                }
+            }
+        }
+
+        let message = self.send(&request).await?;
+        conversation.push(message.clone());
+        Ok(message)
+    }
 
-        let response = self.client
-            .post(&self.base_url)
-            .json(&request)
-            .send()
+    /// Start a streamed completion for the conversation without waiting for
+    /// the reply. `total_timeout` bounds the whole response body.
+    pub async fn stream(
+        &self,
+        conversation: &Conversation,
+        tools: &[ToolDefinition],
+        model: &str,
+        total_timeout: Duration,
+    ) -> Result<TokenStream> {
+        let request = self.request(conversation, tools, model, true).await;
+        let response = self
+            .post(
+                || self.provider.chat_request(&self.client, &request),
+                total_timeout,
+            )
             .await?;
 
+        Ok(TokenStream {
+            response,
+            provider: self.provider.clone(),
+            parser: StreamParser::new(self.provider.stream_format()),
+            pending: VecDeque::new(),
+            done: false,
+        })
+    }
+
+    /// Like `converse`, but reads the reply as it is generated: progress is
+    /// logged, a stalled server fails fast, and reading stops early once a
+    /// stop marker appears.
+    pub async fn converse_streaming(
+        &self,
+        conversation: &mut Conversation,
+        tools: &[ToolDefinition],
+        model: &str,
+        options: &StreamOptions,
+    ) -> Result<ChatMessage> {
+        let request = self.request(conversation, tools, model, true).await;
+        if let Some(message) = self.replay(&request)? {
+            conversation.push(message.clone());
+            return Ok(message);
+        }
+
+        let started = Instant::now();
+        let deadline = started + options.total_timeout;
+        let mut stream = self
+            .stream(conversation, tools, model, options.total_timeout)
+            .await?;
+
+        let mut content = String::new();
+        let mut tool_calls: Vec<ToolCall> = Vec::new();
+        let mut deltas = 0usize;
+        let mut last_progress = Instant::now();
+
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let wait = options.idle_timeout.min(remaining);
+
+            let delta = match tokio::time::timeout(wait, stream.next_delta()).await {
+                Ok(delta) => delta?,
+                Err(_) if wait < options.idle_timeout => {
+                    let reason = format!(
+                        "exceeded total timeout of {}s",
+                        options.total_timeout.as_secs()
+                    );
+                    return Err(LlmError::Timeout(reason).into());
+                }
+                Err(_) => {
+                    let reason = format!("idle for {}s", options.idle_timeout.as_secs());
+                    return Err(LlmError::Timeout(reason).into());
+                }
+            };
+            let Some(delta) = delta else { break };
+            deltas += 1;
+
+            if let Some(text) = delta.content {
+                content.push_str(&text);
+            }
+            for call in delta.tool_calls.unwrap_or_default() {
+                merge_tool_call(&mut tool_calls, call);
+            }
+
+            if last_progress.elapsed() >= Duration::from_secs(10) {
+                info!(
+                    "LLM streaming: {} chunks, {} chars in {}s",
+                    deltas,
+                    content.len(),
+                    started.elapsed().as_secs()
+                );
+                last_progress = Instant::now();
+            }
+
+            if let Some(marker) = options
+                .stop_markers
+                .iter()
+                .find(|m| content.contains(m.as_str()))
+            {
+                // Dropping the stream closes the connection and stops generation
+                info!("Verdict marker {} seen, cancelling stream", marker);
+                break;
+            }
+        }
+
+        debug!(
+            "LLM stream finished: {} chunks in {}s",
+            deltas,
+            started.elapsed().as_secs()
+        );
+
+        let message = ChatMessage {
+            role: "assistant".to_string(),
+            content: if content.is_empty() && !tool_calls.is_empty() {
+                None
+            } else {
+                Some(content)
+            },
+            tool_calls: if tool_calls.is_empty() {
+                None
+            } else {
+                Some(tool_calls)
+            },
+            tool_call_id: None,
+        };
+        self.record(&request, &message)?;
+        conversation.push(message.clone());
+        Ok(message)
+    }
+
+    fn replay(&self, request: &ChatCompletionRequest) -> Result<Option<ChatMessage>> {
+        match &self.cassette {
+            Some(cassette) => cassette.replay(request),
+            None => Ok(None),
+        }
+    }
+
+    fn record(&self, request: &ChatCompletionRequest, reply: &ChatMessage) -> Result<()> {
+        match &self.cassette {
+            Some(cassette) => cassette.record(request, reply),
+            None => Ok(()),
+        }
+    }
+
+    /// Build the request for a conversation, trimming the copy of its
+    /// messages to what fits `model`'s window next to the tools and the
+    /// reply. The conversation itself keeps the full history.
+    async fn request(
+        &self,
+        conversation: &Conversation,
+        tools: &[ToolDefinition],
+        model: &str,
+        stream: bool,
+    ) -> ChatCompletionRequest {
+        let reserved = conversation.params.max_tokens.unwrap_or(0) as usize + tools_tokens(tools);
+        let budget = self.context_length(model).await.saturating_sub(reserved);
+
+        let mut messages = conversation.messages.clone();
+        fit_messages(&mut messages, budget);
+
+        ChatCompletionRequest {
+            model: model.to_string(),
+            messages,
+            params: conversation.params.clone(),
+            tools: if tools.is_empty() {
+                None
+            } else {
+                Some(tools.to_vec())
+            },
+            stream,
+            response_format: None,
+        }
+    }
+
+    /// POST with retries for transient failures, failing fast while the
+    /// circuit breaker is open.
+    async fn post(
+        &self,
+        request: impl Fn() -> RequestBuilder,
+        timeout: Duration,
+    ) -> Result<reqwest::Response> {
+        match self.breaker.state() {
+            BreakerState::Closed => {}
+            BreakerState::Open => return Err(LlmError::BackendUnavailable.into()),
+            BreakerState::HalfOpen => {
+                if !self.is_available().await {
+                    self.breaker.record_failure();
+                    return Err(LlmError::BackendUnavailable.into());
+                }
+            }
+        }
+
+        let mut attempt = 0;
+        loop {
+            match self.post_once(request(), timeout).await {
+                Ok(response) => {
+                    self.breaker.record_success();
+                    return Ok(response);
+                }
+                Err(e) if is_retryable(&e) && attempt + 1 < self.retry.max_attempts => {
+                    let delay = self.retry.delay(attempt);
+                    warn!(
+                        "LLM request failed ({}), retry {}/{} in {:.1}s",
+                        e,
+                        attempt + 1,
+                        self.retry.max_attempts - 1,
+                        delay.as_secs_f64()
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => {
+                    if is_retryable(&e) {
+                        self.breaker.record_failure();
+                    }
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    async fn post_once(
+        &self,
+        request: RequestBuilder,
+        timeout: Duration,
+    ) -> Result<reqwest::Response> {
+        let response = request.timeout(timeout).send().await?;
+
         if !response.status().is_success() {
             let status = response.status();
             let body = response.text().await.unwrap_or_default();
-            return Err(anyhow::anyhow!("LLM request failed: {} - {}", status, body));
+            return Err(LlmError::Status {
+                status: status.as_u16(),
+                body,
+            }
+            .into());
+        }
+
+        Ok(response)
+    }
+
+    async fn send(&self, request: &ChatCompletionRequest) -> Result<ChatMessage> {
+        if let Some(message) = self.replay(request)? {
+            return Ok(message);
+        }
+
+        let response = self
+            .post(
+                || self.provider.chat_request(&self.client, request),
+                REQUEST_TIMEOUT,
+            )
+            .await?;
+        let body = response.text().await?;
+        let message = self.provider.parse_reply(&body)?;
+        self.record(request, &message)?;
+        Ok(message)
+    }
+
+    /// Embed each of `input` with `model`, one vector per input.
+    pub async fn embed(&self, model: &str, input: &[String]) -> Result<Vec<Vec<f32>>> {
+        if let Some(vectors) = self
+            .cassette
+            .as_ref()
+            .map(|c| c.replay_embeddings(model, input))
+            .transpose()?
+            .flatten()
+        {
+            return Ok(vectors);
+        }
+
+        let response = self
+            .post(
+                || self.provider.embeddings_request(&self.client, model, input),
+                REQUEST_TIMEOUT,
+            )
+            .await?;
+        let vectors = self.provider.parse_embeddings(&response.text().await?)?;
+        if vectors.len() != input.len() {
+            return Err(anyhow::anyhow!(
+                "Asked for {} embeddings, got {}",
+                input.len(),
+                vectors.len()
+            ));
         }
 
-        let completion: ChatCompletionResponse = response.json().await?;
-        let content = completion.choices
-            .into_iter()
-            .next()
-            .ok_or_else(|| anyhow::anyhow!("No choices in LLM response"))?
-            .message
-            .content;
+        if let Some(cassette) = &self.cassette {
+            cassette.record_embeddings(model, input, &vectors)?;
+        }
+        Ok(vectors)
+    }
+
+    /// Poll the health check with backoff until the backend answers or
+    /// `max_wait` runs out. Returns whether it is available.
+    pub async fn wait_until_available(&self, max_wait: Duration) -> bool {
+        let deadline = Instant::now() + max_wait;
+        let mut attempt = 0;
 
-        Ok(content)
+        loop {
+            if self.is_available().await {
+                self.breaker.record_success();
+                return true;
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return false;
+            }
+
+            let delay = self
+                .retry
+                .delay(attempt)
+                .max(Duration::from_secs(1))
+                .min(remaining);
+            info!(
+                "LLM backend unavailable, checking again in {:.0}s",
+                delay.as_secs_f64()
+            );
+            tokio::time::sleep(delay).await;
+            attempt = (attempt + 1).min(16);
+        }
     }
 
     pub async fn is_available(&self) -> bool {
-        // Simple health check
-        self.client
-            .get(&format!("{}/models", self.base_url.trim_end_matches("/v1/chat/completions")))
-            .send()
-            .await
-            .map(|r| r.status().is_success())
-            .unwrap_or(false)
-    }
-}
\ No newline at end of file
+        if self
+            .cassette
+            .as_ref()
+            .is_some_and(|c| c.mode() == CassetteMode::Replay)
+        {
+            return true;
+        }
+        self.provider.is_healthy(&self.client).await
+    }
+
+    /// Models the backend can serve right now.
+    pub async fn list_models(&self) -> Result<Vec<String>> {
+        if let Some(models) = self
+            .cassette
+            .as_ref()
+            .map(|c| c.replay_models())
+            .transpose()?
+            .flatten()
+        {
+            return Ok(models);
+        }
+
+        let models = self.provider.list_models(&self.client).await?;
+        if let Some(cassette) = &self.cassette {
+            cassette.record_models(&models)?;
+        }
+        Ok(models)
+    }
+
+    pub fn provider_name(&self) -> &'static str {
+        self.provider.name()
+    }
+}
+
+/// Fold a streamed tool-call fragment into the calls assembled so far.
+fn merge_tool_call(calls: &mut Vec<ToolCall>, delta: ToolCallDelta) {
+    while calls.len() <= delta.index {
+        calls.push(ToolCall {
+            id: String::new(),
+            kind: default_tool_type(),
+            function: FunctionCall {
+                name: String::new(),
+                arguments: String::new(),
+            },
+        });
+    }
+
+    let call = &mut calls[delta.index];
+    if let Some(id) = delta.id {
+        call.id = id;
+    }
+    if let Some(function) = delta.function {
+        if let Some(name) = function.name {
+            call.function.name.push_str(&name);
+        }
+        if let Some(arguments) = function.arguments {
+            call.function.arguments.push_str(&arguments);
+        }
+    }
+}
@@ -0,0 +1,331 @@
+use crate::app::LogLine;
+use crate::verdict::{Finding, Severity};
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Lines of each cluster's first occurrence kept as an example.
+const EXAMPLE_LINES: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogIssueKind {
+    /// An error with a stack trace, or an uncaught exception
+    Exception,
+    UnhandledRejection,
+    /// A request the app answered with a 5xx status
+    ServerError,
+    Deprecation,
+}
+
+impl LogIssueKind {
+    fn severity(self) -> Severity {
+        match self {
+            LogIssueKind::Exception
+            | LogIssueKind::UnhandledRejection
+            | LogIssueKind::ServerError => Severity::Major,
+            LogIssueKind::Deprecation => Severity::Minor,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            LogIssueKind::Exception => "exception",
+            LogIssueKind::UnhandledRejection => "unhandled-rejection",
+            LogIssueKind::ServerError => "server-error",
+            LogIssueKind::Deprecation => "deprecation",
+        }
+    }
+}
+
+/// Occurrences of one problem in the app's output, grouped by signature:
+/// the kind, the message with its variable parts masked, and where in the
+/// app's own code it came from.
+#[derive(Debug, Clone)]
+pub struct LogCluster {
+    pub kind: LogIssueKind,
+    pub signature: String,
+    /// Message of the first occurrence
+    pub message: String,
+    pub count: usize,
+    /// Innermost frame in the workspace, relative to it
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub example: Vec<String>,
+}
+
+impl LogCluster {
+    pub fn finding(&self) -> Finding {
+        let times = if self.count == 1 {
+            String::new()
+        } else {
+            format!(" ({} times)", self.count)
+        };
+        let message = format!(
+            "App logged {}{}: {}\n{}",
+            self.kind.name().replace('-', " "),
+            times,
+            self.message,
+            self.example.join("\n")
+        );
+        let finding =
+            Finding::new(self.kind.severity(), message).rule(format!("log:{}", self.kind.name()));
+        match &self.file {
+            Some(file) => finding.at(file.clone(), self.line),
+            None => finding,
+        }
+    }
+}
+
+/// One problem found in the log before clustering.
+struct Occurrence {
+    kind: LogIssueKind,
+    message: String,
+    frames: Vec<(String, u32)>,
+    lines: Vec<String>,
+}
+
+struct Patterns {
+    js_error: Regex,
+    js_frame: Regex,
+    python_frame: Regex,
+    python_error: Regex,
+    deprecation: Regex,
+    access: Regex,
+    digits: Regex,
+    quoted: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| Patterns {
+        js_error: Regex::new(r"^\s*(?:Uncaught\s+)?((?:[A-Z]\w*)?(?:Error|Exception))(?:\s*\[[\w-]+\])?:\s*(.*)$").unwrap(),
+        js_frame: Regex::new(r"^\s+at\s+(?:.*?\()?(?:file://)?([^()\s]+?):(\d+):\d+\)?\s*$").unwrap(),
+        python_frame: Regex::new(r#"^\s+File "([^"]+)", line (\d+)"#).unwrap(),
+        python_error: Regex::new(r"^([A-Za-z_][\w.]*(?:Error|Exception|Exit|Interrupt|Warning)):?\s*(.*)$").unwrap(),
+        deprecation: Regex::new(r"(?:\[(DEP\d+)\]\s*)?DeprecationWarning:\s*(.*)|npm (?:WARN|warn) deprecated\s+(.*)").unwrap(),
+        access: Regex::new(r#"\b(GET|POST|PUT|PATCH|DELETE|HEAD|OPTIONS)\s+(\S+?)(?:\s+HTTP/[\d.]+"?)?\s+(5\d\d)\b"#).unwrap(),
+        digits: Regex::new(r"\b0x[0-9a-fA-F]+\b|\b[0-9a-fA-F]{8}-[0-9a-fA-F-]{27}\b|\d+").unwrap(),
+        quoted: Regex::new(r#""[^"]*"|'[^']*'|`[^`]*`"#).unwrap(),
+    })
+}
+
+/// Find exceptions, stack traces, unhandled rejections, deprecation
+/// warnings and 5xx access-log lines in the app's output, clustered by
+/// signature, most frequent first.
+pub fn analyze(logs: &[LogLine], workspace: &Path) -> Vec<LogCluster> {
+    let mut clusters: BTreeMap<String, LogCluster> = BTreeMap::new();
+
+    for occurrence in scan(logs) {
+        let location = occurrence
+            .frames
+            .iter()
+            .find_map(|(path, line)| workspace_path(path, workspace).map(|path| (path, *line)));
+        let signature = signature(&occurrence, location.as_ref());
+
+        clusters
+            .entry(signature.clone())
+            .and_modify(|cluster| cluster.count += 1)
+            .or_insert_with(|| LogCluster {
+                kind: occurrence.kind,
+                signature,
+                message: occurrence.message.clone(),
+                count: 1,
+                file: location.as_ref().map(|(path, _)| path.clone()),
+                line: location.map(|(_, line)| line),
+                example: occurrence.lines.into_iter().take(EXAMPLE_LINES).collect(),
+            });
+    }
+
+    let mut clusters: Vec<LogCluster> = clusters.into_values().collect();
+    clusters.sort_by(|a, b| a.kind.cmp(&b.kind).then(b.count.cmp(&a.count)));
+    clusters
+}
+
+/// One line per kind with how often it occurred.
+pub fn summary(clusters: &[LogCluster]) -> String {
+    if clusters.is_empty() {
+        return "app logs: clean".to_string();
+    }
+
+    let mut counts: BTreeMap<LogIssueKind, (usize, usize)> = BTreeMap::new();
+    for cluster in clusters {
+        let entry = counts.entry(cluster.kind).or_default();
+        entry.0 += 1;
+        entry.1 += cluster.count;
+    }
+    let parts: Vec<String> = counts
+        .iter()
+        .map(|(kind, (distinct, total))| {
+            format!("{} {} ({} distinct)", total, kind.name(), distinct)
+        })
+        .collect();
+    format!("app logs: {}", parts.join(", "))
+}
+
+fn scan(logs: &[LogLine]) -> Vec<Occurrence> {
+    let patterns = patterns();
+    let mut found = Vec::new();
+    // Node prints `triggerUncaughtException(err, true /* fromPromise */)`
+    // above the error when a rejection went unhandled
+    let mut from_promise = false;
+    let mut index = 0;
+
+    while index < logs.len() {
+        let text = logs[index].text.as_str();
+
+        if text.contains("fromPromise") {
+            from_promise = true;
+        }
+
+        if text.contains("UnhandledPromiseRejection")
+            || text.contains("Unhandled promise rejection")
+            || text.contains("unhandledRejection")
+        {
+            found.push(Occurrence {
+                kind: LogIssueKind::UnhandledRejection,
+                message: text.trim().trim_start_matches('[').to_string(),
+                frames: Vec::new(),
+                lines: vec![text.to_string()],
+            });
+            from_promise = false;
+            index += 1;
+            continue;
+        }
+
+        if text.starts_with("Traceback (most recent call last)") {
+            let (occurrence, next) = python_traceback(logs, index);
+            found.push(occurrence);
+            index = next;
+            continue;
+        }
+
+        if let Some(captures) = patterns.js_error.captures(text) {
+            let mut lines = vec![text.to_string()];
+            let mut frames = Vec::new();
+            let mut next = index + 1;
+            while let Some(frame) = logs
+                .get(next)
+                .and_then(|l| patterns.js_frame.captures(&l.text))
+            {
+                lines.push(logs[next].text.clone());
+                frames.push((frame[1].to_string(), frame[2].parse().unwrap_or(0)));
+                next += 1;
+            }
+
+            // A bare `Error: ...` on stdout is usually the app describing an
+            // error it handled; only count it with a stack or on stderr
+            if !frames.is_empty() || logs[index].stderr {
+                let kind = if from_promise {
+                    LogIssueKind::UnhandledRejection
+                } else {
+                    LogIssueKind::Exception
+                };
+                found.push(Occurrence {
+                    kind,
+                    message: format!("{}: {}", &captures[1], captures[2].trim()),
+                    frames,
+                    lines,
+                });
+                from_promise = false;
+            }
+            index = next;
+            continue;
+        }
+
+        if let Some(captures) = patterns.deprecation.captures(text) {
+            let message = captures
+                .get(2)
+                .or_else(|| captures.get(3))
+                .map(|m| m.as_str().trim())
+                .unwrap_or(text);
+            let message = match captures.get(1) {
+                Some(code) => format!("{} {}", code.as_str(), message),
+                None => message.to_string(),
+            };
+            found.push(Occurrence {
+                kind: LogIssueKind::Deprecation,
+                message,
+                frames: Vec::new(),
+                lines: vec![text.to_string()],
+            });
+        } else if let Some(captures) = patterns.access.captures(text) {
+            found.push(Occurrence {
+                kind: LogIssueKind::ServerError,
+                message: format!("{} {} -> {}", &captures[1], &captures[2], &captures[3]),
+                frames: Vec::new(),
+                lines: vec![text.to_string()],
+            });
+        }
+
+        index += 1;
+    }
+
+    found
+}
+
+/// A Python traceback starting at `start`; returns it and the index after it.
+fn python_traceback(logs: &[LogLine], start: usize) -> (Occurrence, usize) {
+    let patterns = patterns();
+    let mut lines = vec![logs[start].text.clone()];
+    let mut frames = Vec::new();
+    let mut message = "Traceback".to_string();
+    let mut index = start + 1;
+
+    while let Some(line) = logs.get(index) {
+        lines.push(line.text.clone());
+        index += 1;
+
+        if let Some(frame) = patterns.python_frame.captures(&line.text) {
+            frames.push((frame[1].to_string(), frame[2].parse().unwrap_or(0)));
+        } else if let Some(error) = patterns.python_error.captures(&line.text) {
+            message = format!("{}: {}", &error[1], error[2].trim());
+            break;
+        } else if !line.text.starts_with(' ') {
+            break;
+        }
+    }
+
+    // Python lists the innermost frame last
+    frames.reverse();
+    let lines = lines.split_off(lines.len().saturating_sub(EXAMPLE_LINES));
+    (
+        Occurrence {
+            kind: LogIssueKind::Exception,
+            message,
+            frames,
+            lines,
+        },
+        index,
+    )
+}
+
+/// `path` relative to the workspace if it is the app's own code.
+fn workspace_path(path: &str, workspace: &Path) -> Option<String> {
+    if path.starts_with("node:") || path.contains("node_modules") || path.contains("site-packages")
+    {
+        return None;
+    }
+
+    let path = Path::new(path);
+    if path.is_relative() {
+        return Some(path.to_string_lossy().replace('\\', "/"));
+    }
+
+    let workspace = workspace
+        .canonicalize()
+        .unwrap_or_else(|_| workspace.to_path_buf());
+    path.strip_prefix(&workspace)
+        .ok()
+        .map(|relative| relative.to_string_lossy().replace('\\', "/"))
+}
+
+fn signature(occurrence: &Occurrence, location: Option<&(String, u32)>) -> String {
+    let patterns = patterns();
+    let masked = patterns.quoted.replace_all(&occurrence.message, "<s>");
+    let masked = patterns.digits.replace_all(&masked, "N");
+    match location {
+        Some((path, line)) => format!("{}|{}|{}:{}", occurrence.kind.name(), masked, path, line),
+        None => format!("{}|{}", occurrence.kind.name(), masked),
+    }
+}
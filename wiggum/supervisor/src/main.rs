@@ -1,7 +1,17 @@
 use clap::{Parser, Subcommand};
+use ralph_wiggum_supervisor::acceptance::AcceptanceSuite;
+use ralph_wiggum_supervisor::architecture::{ArchitectureReport, GraphFormat};
+use ralph_wiggum_supervisor::cassette::CassetteMode;
+use ralph_wiggum_supervisor::clones::DEFAULT_MIN_CLONE_TOKENS;
+use ralph_wiggum_supervisor::mock_server::{MockScript, MockServer};
+use ralph_wiggum_supervisor::provider::ProviderKind;
+use ralph_wiggum_supervisor::supervisor::{
+    DEFAULT_AGENT_MODEL, DEFAULT_MAX_ITERATIONS, DEFAULT_TRUNK_MODEL,
+};
+use ralph_wiggum_supervisor::traceability::TraceabilityReport;
 use ralph_wiggum_supervisor::{Supervisor, SupervisorConfig};
 use std::path::PathBuf;
-use tracing::{info, error};
+use tracing::info;
 
 #[derive(Parser)]
 #[command(name = "ralph-wiggum-supervisor")]
@@ -18,15 +28,81 @@ enum Commands {
         /// Path to the state directory
         #[arg(long, default_value = "../state")]
         state_dir: PathBuf,
+        /// Kind of local inference server the agents talk to
+        #[arg(long, value_enum, default_value_t = ProviderKind::Openai)]
+        provider: ProviderKind,
+        /// API root of the inference server (defaults to the provider's usual port)
+        #[arg(long)]
+        llm_url: Option<String>,
+        /// Model the verification agents use
+        #[arg(long, default_value = DEFAULT_AGENT_MODEL)]
+        agent_model: String,
+        /// Fallback for --agent-model when it is not loaded; repeat for a longer chain
+        #[arg(long = "fallback-model")]
+        fallback_models: Vec<String>,
+        /// Re-run gates that passed on a fallback model once the primary is back
+        #[arg(long)]
+        rerun_fallback_gates: bool,
+        /// Cassette file for recording or replaying LLM traffic
+        #[arg(long, requires = "cassette_mode")]
+        cassette: Option<PathBuf>,
+        /// Whether --cassette is recorded or replayed
+        #[arg(long, value_enum, requires = "cassette")]
+        cassette_mode: Option<CassetteMode>,
+        /// Embedding model for retrieving relevant workspace code into agent prompts
+        #[arg(long)]
+        embedding_model: Option<String>,
+        /// Model used by opencode for the development phase
+        #[arg(long, default_value = DEFAULT_TRUNK_MODEL)]
+        trunk_model: String,
+        /// Give up after this many failed development iterations
+        #[arg(long, default_value_t = DEFAULT_MAX_ITERATIONS)]
+        max_iterations: u32,
+        /// Shortest duplicated token run the code slop gate reports
+        #[arg(long, default_value_t = DEFAULT_MIN_CLONE_TOKENS)]
+        min_clone_tokens: usize,
     },
     /// Initialize a new development session
     Init {
         /// User intent description
         intent: String,
+        /// Acceptance criterion (repeatable); defaults to the intent's bulleted lines
+        #[arg(long = "criterion")]
+        criteria: Vec<String>,
         /// Path to state directory
         #[arg(long, default_value = "../state")]
         state_dir: PathBuf,
     },
+    /// Freeze the synthesised acceptance tests as the definition of done
+    ApproveTests {
+        /// Path to state directory
+        #[arg(long, default_value = "../state")]
+        state_dir: PathBuf,
+    },
+    /// Show which acceptance criteria have passing evidence
+    Trace {
+        /// Path to state directory
+        #[arg(long, default_value = "../state")]
+        state_dir: PathBuf,
+    },
+    /// Print the module dependency graph from the last architecture check
+    Graph {
+        /// Path to state directory
+        #[arg(long, default_value = "../state")]
+        state_dir: PathBuf,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = GraphFormat::Dot)]
+        format: GraphFormat,
+    },
+    /// Serve a scripted OpenAI-compatible API for offline runs
+    MockLlm {
+        /// JSON script of rules (see mock_server::MockScript)
+        #[arg(long)]
+        script: Option<PathBuf>,
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:1234")]
+        listen: std::net::SocketAddr,
+    },
 }
 
 #[tokio::main]
@@ -39,29 +115,115 @@ async fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Tick { state_dir } => {
+        Commands::Tick {
+            state_dir,
+            provider,
+            llm_url,
+            agent_model,
+            fallback_models,
+            rerun_fallback_gates,
+            cassette,
+            cassette_mode,
+            embedding_model,
+            trunk_model,
+            max_iterations,
+            min_clone_tokens,
+        } => {
             info!("Running supervisor tick");
 
             let config = SupervisorConfig {
                 state_dir,
-                lm_studio_url: "http://localhost:1234/v1/chat/completions".to_string(),
+                llm_provider: provider,
+                llm_url: llm_url.unwrap_or_else(|| provider.default_url().to_string()),
+                agent_model,
+                fallback_models,
+                rerun_fallback_gates,
+                cassette: cassette.zip(cassette_mode),
+                embedding_model,
+                trunk_model,
+                max_iterations,
+                min_clone_tokens,
             };
 
             let mut supervisor = Supervisor::new(config).await?;
             supervisor.tick().await?;
         }
-        Commands::Init { intent, state_dir } => {
+        Commands::Init {
+            intent,
+            criteria,
+            state_dir,
+        } => {
             info!("Initializing new development session");
 
             let config = SupervisorConfig {
                 state_dir: state_dir.clone(),
-                lm_studio_url: "http://localhost:1234/v1/chat/completions".to_string(),
+                llm_provider: ProviderKind::Openai,
+                llm_url: ProviderKind::Openai.default_url().to_string(),
+                agent_model: DEFAULT_AGENT_MODEL.to_string(),
+                fallback_models: Vec::new(),
+                rerun_fallback_gates: false,
+                cassette: None,
+                embedding_model: None,
+                trunk_model: DEFAULT_TRUNK_MODEL.to_string(),
+                max_iterations: DEFAULT_MAX_ITERATIONS,
+                min_clone_tokens: DEFAULT_MIN_CLONE_TOKENS,
             };
 
-            Supervisor::initialize(intent, config).await?;
+            Supervisor::initialize(intent, criteria, config).await?;
             info!("Session initialized. Run 'tick' to start development.");
         }
+        Commands::ApproveTests { state_dir } => {
+            let mut suite = AcceptanceSuite::load(&state_dir)?.ok_or_else(|| {
+                anyhow::anyhow!(
+                    "No acceptance tests in {}; run a tick to synthesise them",
+                    state_dir.display()
+                )
+            })?;
+
+            for test in &suite.tests {
+                info!("{}: {}", test.id, test.description);
+            }
+            suite.approve()?;
+            suite.save(&state_dir)?;
+            info!("Approved {} acceptance tests", suite.tests.len());
+        }
+        Commands::Trace { state_dir } => {
+            let report = TraceabilityReport::load(&state_dir)?.ok_or_else(|| {
+                anyhow::anyhow!(
+                    "No traceability report in {}; run the verification gate first",
+                    state_dir.display()
+                )
+            })?;
+
+            println!("{}", report.render());
+            info!(
+                "{} of {} criteria have passing evidence",
+                report.criteria.len() - report.unmet().len(),
+                report.criteria.len()
+            );
+        }
+        Commands::Graph { state_dir, format } => {
+            let report = ArchitectureReport::load(&state_dir)?.ok_or_else(|| {
+                anyhow::anyhow!(
+                    "No architecture report in {}; run the architecture gate first",
+                    state_dir.display()
+                )
+            })?;
+
+            println!("{}", report.render(format)?);
+            info!("{}", report.summary());
+        }
+        Commands::MockLlm { script, listen } => {
+            let script = match script {
+                Some(path) => MockScript::load(&path)?,
+                None => MockScript::default(),
+            };
+
+            let server = MockServer::start(script, listen).await?;
+            info!("Point --llm-url at {}", server.base_url());
+            server.wait().await?;
+        }
     }
 
     Ok(())
-}
\ No newline at end of file
+}
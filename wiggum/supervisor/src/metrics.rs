@@ -0,0 +1,556 @@
+use crate::tools::SKIPPED_DIRS;
+use crate::verdict::{Finding, Severity};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tree_sitter::{Language, Node, Parser};
+use walkdir::WalkDir;
+
+/// Generated and vendored files are not measured.
+const MAX_SOURCE_BYTES: u64 = 256 * 1024;
+
+/// Limits above which a function or file is reported.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricThresholds {
+    pub max_cyclomatic: u32,
+    pub max_cognitive: u32,
+    pub max_nesting: u32,
+    pub max_function_lines: u32,
+    pub max_file_loc: u32,
+}
+
+impl Default for MetricThresholds {
+    fn default() -> Self {
+        Self {
+            max_cyclomatic: 10,
+            max_cognitive: 15,
+            max_nesting: 4,
+            max_function_lines: 60,
+            max_file_loc: 400,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionMetrics {
+    pub name: String,
+    pub start_line: u32,
+    pub lines: u32,
+    pub cyclomatic: u32,
+    pub cognitive: u32,
+    /// Deepest nesting of control flow inside the function
+    pub nesting: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileMetrics {
+    /// Path relative to the workspace, with `/` separators
+    pub path: String,
+    /// Lines that are neither blank nor only a comment
+    pub loc: u32,
+    pub functions: Vec<FunctionMetrics>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MetricTotals {
+    pub files: usize,
+    pub loc: u32,
+    pub functions: usize,
+    pub mean_cyclomatic: f64,
+    pub max_cyclomatic: u32,
+    pub mean_cognitive: f64,
+    pub violations: usize,
+}
+
+/// The workspace's code metrics at one loop iteration
+/// (state/metrics/iteration-N.json), kept so trends can be compared.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricsSnapshot {
+    pub iteration: u64,
+    pub taken_at: DateTime<Utc>,
+    pub totals: MetricTotals,
+    pub files: Vec<FileMetrics>,
+}
+
+pub fn metrics_dir(state_dir: &Path) -> PathBuf {
+    state_dir.join("metrics")
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum Grammar {
+    JavaScript,
+    TypeScript,
+    Tsx,
+    Rust,
+}
+
+impl Grammar {
+    pub(crate) fn for_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "js" | "mjs" | "cjs" | "jsx" | "vue" => Some(Grammar::JavaScript),
+            "ts" | "mts" | "cts" => Some(Grammar::TypeScript),
+            "tsx" => Some(Grammar::Tsx),
+            "rs" => Some(Grammar::Rust),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn language(self) -> Language {
+        match self {
+            Grammar::JavaScript => tree_sitter_javascript::LANGUAGE.into(),
+            Grammar::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            Grammar::Tsx => tree_sitter_typescript::LANGUAGE_TSX.into(),
+            Grammar::Rust => tree_sitter_rust::LANGUAGE.into(),
+        }
+    }
+}
+
+impl MetricsSnapshot {
+    /// Parse every JS/TS/Vue/Rust source in the workspace and measure it.
+    pub fn take(root: &Path, iteration: u64, thresholds: &MetricThresholds) -> Result<Self> {
+        let walker = WalkDir::new(root)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|e| !SKIPPED_DIRS.contains(&e.file_name().to_string_lossy().as_ref()));
+
+        let mut files = Vec::new();
+        for entry in walker
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+        {
+            let path = entry.path();
+            let Some(grammar) = Grammar::for_path(path) else {
+                continue;
+            };
+            let small = entry
+                .metadata()
+                .map(|m| m.len() <= MAX_SOURCE_BYTES)
+                .unwrap_or(false);
+            if !small || entry.file_name().to_string_lossy().contains(".min.") {
+                continue;
+            }
+            let Ok(source) = fs::read_to_string(path) else {
+                continue;
+            };
+
+            let relative = path
+                .strip_prefix(root)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            files.push(measure_file(relative, &source, grammar)?);
+        }
+
+        let mut snapshot = Self {
+            iteration,
+            taken_at: Utc::now(),
+            totals: MetricTotals::default(),
+            files,
+        };
+        snapshot.totals = snapshot.compute_totals(thresholds);
+        Ok(snapshot)
+    }
+
+    /// The most recent snapshot saved before `iteration`, if any.
+    pub fn previous(state_dir: &Path, iteration: u64) -> Result<Option<Self>> {
+        let dir = metrics_dir(state_dir);
+        if !dir.exists() {
+            return Ok(None);
+        }
+
+        let latest = fs::read_dir(&dir)?
+            .filter_map(|e| e.ok())
+            .filter_map(|e| {
+                let name = e.file_name().to_string_lossy().to_string();
+                let number = name
+                    .strip_prefix("iteration-")?
+                    .strip_suffix(".json")?
+                    .parse::<u64>()
+                    .ok()?;
+                (number < iteration).then_some((number, e.path()))
+            })
+            .max_by_key(|(number, _)| *number);
+
+        match latest {
+            Some((_, path)) => Ok(Some(serde_json::from_str(&fs::read_to_string(path)?)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn save(&self, state_dir: &Path) -> Result<()> {
+        let dir = metrics_dir(state_dir);
+        fs::create_dir_all(&dir)?;
+        fs::write(
+            dir.join(format!("iteration-{}.json", self.iteration)),
+            serde_json::to_string_pretty(self)?,
+        )?;
+        Ok(())
+    }
+
+    /// Totals, with the change since `previous` when there is one.
+    pub fn summary(&self, previous: Option<&MetricsSnapshot>) -> String {
+        let totals = &self.totals;
+        let mut out = format!(
+            "{} files, {} LOC, {} functions, cyclomatic mean {:.1} max {}, cognitive mean {:.1}, {} threshold violations",
+            totals.files, totals.loc, totals.functions, totals.mean_cyclomatic, totals.max_cyclomatic, totals.mean_cognitive, totals.violations
+        );
+        if let Some(previous) = previous {
+            let before = &previous.totals;
+            out.push_str(&format!(
+                " (since iteration {}: LOC {:+}, cyclomatic mean {:+.1}, cognitive mean {:+.1}, violations {:+})",
+                previous.iteration,
+                i64::from(totals.loc) - i64::from(before.loc),
+                totals.mean_cyclomatic - before.mean_cyclomatic,
+                totals.mean_cognitive - before.mean_cognitive,
+                totals.violations as i64 - before.violations as i64
+            ));
+        }
+        out
+    }
+
+    /// One finding per threshold a function or file exceeds.
+    pub fn findings(&self, thresholds: &MetricThresholds) -> Vec<Finding> {
+        let mut findings = Vec::new();
+
+        for file in &self.files {
+            if file.loc > thresholds.max_file_loc {
+                findings.push(
+                    Finding::new(
+                        Severity::Major,
+                        format!(
+                            "File has {} lines of code (limit {}); split it by responsibility",
+                            file.loc, thresholds.max_file_loc
+                        ),
+                    )
+                    .at(file.path.clone(), None)
+                    .rule("slop:file-size"),
+                );
+            }
+
+            for function in &file.functions {
+                let checks = [
+                    (
+                        function.cyclomatic,
+                        thresholds.max_cyclomatic,
+                        Severity::Major,
+                        "cyclomatic",
+                        "cyclomatic complexity",
+                    ),
+                    (
+                        function.cognitive,
+                        thresholds.max_cognitive,
+                        Severity::Major,
+                        "cognitive",
+                        "cognitive complexity",
+                    ),
+                    (
+                        function.nesting,
+                        thresholds.max_nesting,
+                        Severity::Minor,
+                        "nesting",
+                        "nesting depth",
+                    ),
+                    (
+                        function.lines,
+                        thresholds.max_function_lines,
+                        Severity::Minor,
+                        "function-length",
+                        "lines",
+                    ),
+                ];
+                for (value, limit, severity, rule, what) in checks {
+                    if value > limit {
+                        findings.push(
+                            Finding::new(
+                                severity,
+                                format!(
+                                    "{} has {} {} (limit {})",
+                                    function.name, value, what, limit
+                                ),
+                            )
+                            .at(file.path.clone(), Some(function.start_line))
+                            .rule(format!("slop:{}", rule)),
+                        );
+                    }
+                }
+            }
+        }
+
+        findings
+    }
+
+    fn compute_totals(&self, thresholds: &MetricThresholds) -> MetricTotals {
+        let functions: Vec<&FunctionMetrics> =
+            self.files.iter().flat_map(|f| &f.functions).collect();
+        let mean = |value: fn(&FunctionMetrics) -> u32| {
+            if functions.is_empty() {
+                0.0
+            } else {
+                functions.iter().map(|f| f64::from(value(f))).sum::<f64>() / functions.len() as f64
+            }
+        };
+
+        MetricTotals {
+            files: self.files.len(),
+            loc: self.files.iter().map(|f| f.loc).sum(),
+            functions: functions.len(),
+            mean_cyclomatic: mean(|f| f.cyclomatic),
+            max_cyclomatic: functions.iter().map(|f| f.cyclomatic).max().unwrap_or(0),
+            mean_cognitive: mean(|f| f.cognitive),
+            violations: self.findings(thresholds).len(),
+        }
+    }
+}
+
+fn measure_file(path: String, source: &str, grammar: Grammar) -> Result<FileMetrics> {
+    // Only the script block of a single-file component is code
+    let (code, line_offset, grammar) = if path.ends_with(".vue") {
+        match vue_scripts(source).into_iter().next() {
+            Some((script, offset, typescript)) => (
+                script,
+                offset,
+                if typescript {
+                    Grammar::TypeScript
+                } else {
+                    grammar
+                },
+            ),
+            None => ("", 0, grammar),
+        }
+    } else {
+        (source, 0, grammar)
+    };
+
+    let mut parser = Parser::new();
+    parser.set_language(&grammar.language())?;
+    let tree = parser
+        .parse(code, None)
+        .ok_or_else(|| anyhow!("could not parse {}", path))?;
+
+    let mut functions = Vec::new();
+    collect_functions(
+        tree.root_node(),
+        code.as_bytes(),
+        line_offset,
+        &mut functions,
+    );
+
+    Ok(FileMetrics {
+        loc: code_lines(source),
+        path,
+        functions,
+    })
+}
+
+/// The `<script>` blocks of a Vue component, each with the line it starts
+/// on and whether it is TypeScript.
+pub(crate) fn vue_scripts(source: &str) -> Vec<(&str, u32, bool)> {
+    let mut scripts = Vec::new();
+    let mut from = 0;
+    while let Some(open) = source[from..].find("<script").map(|at| from + at) {
+        let Some(body) = source[open..].find('>').map(|at| open + at + 1) else {
+            break;
+        };
+        let Some(close) = source[body..].find("</script>").map(|at| body + at) else {
+            break;
+        };
+        let typescript =
+            source[open..body].contains("lang=\"ts\"") || source[open..body].contains("lang='ts'");
+        let offset = source[..body].matches('\n').count() as u32;
+        scripts.push((&source[body..close], offset, typescript));
+        from = close;
+    }
+    scripts
+}
+
+fn code_lines(source: &str) -> u32 {
+    let mut in_block = false;
+    let mut count = 0;
+    for line in source.lines().map(str::trim) {
+        if in_block {
+            in_block = !line.contains("*/");
+            continue;
+        }
+        if line.is_empty() || line.starts_with("//") {
+            continue;
+        }
+        if line.starts_with("/*") {
+            in_block = !line.contains("*/");
+            continue;
+        }
+        count += 1;
+    }
+    count
+}
+
+const FUNCTION_KINDS: [&str; 8] = [
+    "function_declaration",
+    "function_expression",
+    "function",
+    "generator_function_declaration",
+    "generator_function",
+    "arrow_function",
+    "method_definition",
+    "function_item",
+];
+
+/// Structures that add a branch and a nesting level.
+const BRANCH_KINDS: [&str; 13] = [
+    "if_statement",
+    "for_statement",
+    "for_in_statement",
+    "while_statement",
+    "do_statement",
+    "catch_clause",
+    "ternary_expression",
+    "switch_statement",
+    "if_expression",
+    "for_expression",
+    "while_expression",
+    "loop_expression",
+    "match_expression",
+];
+
+fn is_function(node: Node) -> bool {
+    FUNCTION_KINDS.contains(&node.kind()) && node.child_by_field_name("body").is_some()
+}
+
+fn collect_functions(node: Node, source: &[u8], line_offset: u32, out: &mut Vec<FunctionMetrics>) {
+    if is_function(node) {
+        let body = node.child_by_field_name("body").unwrap_or(node);
+        let mut measure = Measure {
+            cyclomatic: 1,
+            cognitive: 0,
+            nesting: 0,
+        };
+        walk_body(body, 0, &mut measure);
+
+        out.push(FunctionMetrics {
+            name: function_name(node, source),
+            start_line: node.start_position().row as u32 + 1 + line_offset,
+            lines: (node.end_position().row - node.start_position().row) as u32 + 1,
+            cyclomatic: measure.cyclomatic,
+            cognitive: measure.cognitive,
+            nesting: measure.nesting,
+        });
+    }
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_functions(child, source, line_offset, out);
+    }
+}
+
+struct Measure {
+    cyclomatic: u32,
+    cognitive: u32,
+    nesting: u32,
+}
+
+/// Count decision points below `node`, not descending into nested
+/// functions (they are measured on their own).
+fn walk_body(node: Node, depth: u32, measure: &mut Measure) {
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        if is_function(child) {
+            continue;
+        }
+
+        let kind = child.kind();
+        let mut child_depth = depth;
+
+        if BRANCH_KINDS.contains(&kind) {
+            // `else if` continues the chain rather than nesting it
+            let chained = child.parent().is_some_and(|p| p.kind() == "else_clause");
+            if chained {
+                measure.cognitive += 1;
+            } else {
+                measure.cognitive += 1 + depth;
+                child_depth = depth + 1;
+                measure.nesting = measure.nesting.max(child_depth);
+            }
+            // Switch and match branch per case, and a bare loop has no condition
+            if !matches!(
+                kind,
+                "switch_statement" | "match_expression" | "loop_expression"
+            ) {
+                measure.cyclomatic += 1;
+            }
+        } else if kind == "else_clause"
+            && !child
+                .named_children(&mut child.walk())
+                .any(|c| c.kind() == "if_statement" || c.kind() == "if_expression")
+        {
+            measure.cognitive += 1;
+        } else if kind == "switch_case" || kind == "match_arm" {
+            measure.cyclomatic += 1;
+        } else if kind == "binary_expression" {
+            if let Some(operator) = logical_operator(child) {
+                measure.cyclomatic += 1;
+                // A run of the same operator counts once
+                let continues_run = child.parent().and_then(logical_operator) == Some(operator);
+                if !continues_run {
+                    measure.cognitive += 1;
+                }
+            }
+        }
+
+        walk_body(child, child_depth, measure);
+    }
+}
+
+fn logical_operator(node: Node) -> Option<&'static str> {
+    if node.kind() != "binary_expression" {
+        return None;
+    }
+    match node.child_by_field_name("operator")?.kind() {
+        "&&" => Some("&&"),
+        "||" => Some("||"),
+        "??" => Some("??"),
+        _ => None,
+    }
+}
+
+/// The function's own name, or what it is assigned to or passed to.
+fn function_name(node: Node, source: &[u8]) -> String {
+    let text = |n: Node| n.utf8_text(source).unwrap_or("").to_string();
+
+    if let Some(name) = node.child_by_field_name("name") {
+        return text(name);
+    }
+
+    let Some(parent) = node.parent() else {
+        return "<anonymous>".to_string();
+    };
+    let named = match parent.kind() {
+        "variable_declarator" => parent.child_by_field_name("name"),
+        "pair" => parent.child_by_field_name("key"),
+        "assignment_expression" => parent.child_by_field_name("left"),
+        _ => None,
+    };
+    if let Some(name) = named {
+        return text(name);
+    }
+
+    // A callback: name it after the call, e.g. `app.get('/todos') callback`
+    if parent.kind() == "arguments" {
+        if let Some(call) = parent.parent().filter(|p| p.kind() == "call_expression") {
+            let callee = call
+                .child_by_field_name("function")
+                .map(text)
+                .unwrap_or_default();
+            let first = parent
+                .named_child(0)
+                .filter(|a| a.id() != node.id() && a.kind() == "string")
+                .map(text);
+            return match first {
+                Some(first) => format!("{}({}) callback", callee, first),
+                None => format!("{} callback", callee),
+            };
+        }
+    }
+
+    "<anonymous>".to_string()
+}
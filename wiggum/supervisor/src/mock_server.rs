@@ -0,0 +1,390 @@
+use crate::verdict::{Finding, Severity, Verdict, VerdictStatus};
+use anyhow::Result;
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use tracing::{debug, info};
+
+/// Scripted behaviour of the mock server (JSON).
+///
+/// ```json
+/// { "models": ["local-model"],
+///   "rules": [
+///     { "pattern": "Execution Verification", "status": 503, "times": 2 },
+///     { "pattern": "Execution Verification", "verdict": "pass", "latency_ms": 200 },
+///     { "pattern": "", "content": "hello" } ] }
+/// ```
+///
+/// Rules are tried in order against the text of every message in the
+/// request; the first one that matches and has uses left answers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MockScript {
+    #[serde(default = "default_models")]
+    pub models: Vec<String>,
+    #[serde(default)]
+    pub rules: Vec<MockRule>,
+}
+
+fn default_models() -> Vec<String> {
+    vec!["local-model".to_string()]
+}
+
+impl Default for MockScript {
+    fn default() -> Self {
+        Self {
+            models: default_models(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MockRule {
+    /// Regex searched for in the request's messages; empty matches anything
+    #[serde(default)]
+    pub pattern: String,
+    /// Only answer this many matching requests, then fall through
+    #[serde(default)]
+    pub times: Option<u32>,
+    /// Wait this long before answering
+    #[serde(default)]
+    pub latency_ms: u64,
+    /// Answer with this HTTP status and `content` as the error body
+    #[serde(default)]
+    pub status: Option<u16>,
+    /// Assistant message text
+    #[serde(default)]
+    pub content: Option<String>,
+    /// Shorthand for a canned gate verdict; `content` becomes its summary
+    #[serde(default)]
+    pub verdict: Option<VerdictStatus>,
+    #[serde(default)]
+    pub tool_calls: Vec<MockToolCall>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MockToolCall {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: Value,
+}
+
+impl MockScript {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn rule(mut self, rule: MockRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+}
+
+impl MockRule {
+    pub fn matching(pattern: &str) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            ..Self::default()
+        }
+    }
+
+    pub fn reply(mut self, content: &str) -> Self {
+        self.content = Some(content.to_string());
+        self
+    }
+
+    pub fn verdict(mut self, status: VerdictStatus) -> Self {
+        self.verdict = Some(status);
+        self
+    }
+
+    pub fn error(mut self, status: u16) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    pub fn times(mut self, times: u32) -> Self {
+        self.times = Some(times);
+        self
+    }
+
+    pub fn latency(mut self, latency: Duration) -> Self {
+        self.latency_ms = latency.as_millis() as u64;
+        self
+    }
+
+    fn assistant_content(&self) -> Option<String> {
+        let Some(status) = self.verdict else {
+            return self.content.clone();
+        };
+
+        let summary = self
+            .content
+            .clone()
+            .unwrap_or_else(|| format!("Mock {:?} verdict", status));
+        let findings = match status {
+            VerdictStatus::Pass => Vec::new(),
+            VerdictStatus::Fail => vec![Finding::new(Severity::Major, summary.clone())],
+        };
+        let verdict = Verdict {
+            status,
+            summary,
+            findings,
+        };
+        serde_json::to_string(&verdict).ok()
+    }
+}
+
+struct ServerState {
+    script: MockScript,
+    patterns: Vec<Regex>,
+    uses: Vec<u32>,
+    requests: Vec<Value>,
+}
+
+/// OpenAI-compatible server on localhost that answers from a `MockScript`,
+/// for exercising `LlmClient`, retries and whole ticks without LM Studio.
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<ServerState>>,
+    handle: JoinHandle<()>,
+}
+
+impl MockServer {
+    /// Serve `script` on `addr`; port 0 picks a free one.
+    pub async fn start(script: MockScript, addr: SocketAddr) -> Result<Self> {
+        let patterns = script
+            .rules
+            .iter()
+            .map(|rule| Regex::new(&rule.pattern))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let state = Arc::new(Mutex::new(ServerState {
+            uses: vec![0; script.rules.len()],
+            script,
+            patterns,
+            requests: Vec::new(),
+        }));
+
+        let app = Router::new()
+            .route("/v1/models", get(list_models))
+            .route("/v1/chat/completions", post(chat_completions))
+            .route("/v1/embeddings", post(embeddings))
+            .with_state(state.clone());
+
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        let handle = tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, app).await {
+                tracing::error!("Mock LLM server stopped: {}", e);
+            }
+        });
+
+        info!("Mock LLM server listening on http://{}/v1", addr);
+        Ok(Self {
+            addr,
+            state,
+            handle,
+        })
+    }
+
+    /// API root to hand to `ProviderKind::Openai`.
+    pub fn base_url(&self) -> String {
+        format!("http://{}/v1", self.addr)
+    }
+
+    /// Every chat completion request received so far, as sent.
+    pub fn requests(&self) -> Vec<Value> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Block until the server task ends.
+    pub async fn wait(mut self) -> Result<()> {
+        Ok((&mut self.handle).await?)
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+async fn list_models(State(state): State<Arc<Mutex<ServerState>>>) -> Json<Value> {
+    let state = state.lock().unwrap();
+    let data = state
+        .script
+        .models
+        .iter()
+        .map(|id| json!({ "id": id, "object": "model" }))
+        .collect::<Vec<_>>();
+    Json(json!({ "object": "list", "data": data }))
+}
+
+async fn chat_completions(
+    State(state): State<Arc<Mutex<ServerState>>>,
+    Json(request): Json<Value>,
+) -> Response {
+    let rule = {
+        let mut state = state.lock().unwrap();
+        state.requests.push(request.clone());
+        pick_rule(&mut state, &request)
+    };
+
+    let Some(rule) = rule else {
+        // 400 is not retried, so a missing rule fails the caller immediately
+        return (
+            StatusCode::BAD_REQUEST,
+            "mock LLM: no rule matched the request",
+        )
+            .into_response();
+    };
+
+    if rule.latency_ms > 0 {
+        tokio::time::sleep(Duration::from_millis(rule.latency_ms)).await;
+    }
+
+    if let Some(status) = rule.status {
+        let status = StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        return (status, rule.content.clone().unwrap_or_default()).into_response();
+    }
+
+    let model = request.get("model").cloned().unwrap_or(Value::Null);
+    let content = rule.assistant_content();
+    let tool_calls = rule
+        .tool_calls
+        .iter()
+        .enumerate()
+        .map(|(index, call)| {
+            json!({
+                "id": format!("call_{}", index),
+                "type": "function",
+                "function": { "name": call.name, "arguments": call.arguments.to_string() }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    if request
+        .get("stream")
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+    {
+        return stream_reply(&model, content, tool_calls);
+    }
+
+    let mut message = json!({ "role": "assistant", "content": content });
+    if !tool_calls.is_empty() {
+        message["tool_calls"] = Value::Array(tool_calls);
+    }
+
+    Json(json!({
+        "object": "chat.completion",
+        "model": model,
+        "choices": [{ "index": 0, "message": message, "finish_reason": "stop" }]
+    }))
+    .into_response()
+}
+
+/// Dimensions of the mock's bag-of-words embeddings.
+const EMBEDDING_DIMENSIONS: usize = 64;
+
+/// Deterministic embeddings: each word is hashed into a bucket, so texts
+/// sharing words come out similar, which is enough to exercise retrieval.
+async fn embeddings(Json(request): Json<Value>) -> Json<Value> {
+    let inputs: Vec<String> = match request.get("input") {
+        Some(Value::String(text)) => vec![text.clone()],
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(Value::as_str)
+            .map(str::to_string)
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let data = inputs
+        .iter()
+        .enumerate()
+        .map(|(index, text)| {
+            let mut vector = vec![0f32; EMBEDDING_DIMENSIONS];
+            for word in text
+                .split(|c: char| !c.is_alphanumeric())
+                .filter(|w| !w.is_empty())
+            {
+                let bucket = word
+                    .to_lowercase()
+                    .bytes()
+                    .fold(0usize, |h, b| h.wrapping_mul(31).wrapping_add(b as usize));
+                vector[bucket % EMBEDDING_DIMENSIONS] += 1.0;
+            }
+            json!({ "object": "embedding", "index": index, "embedding": vector })
+        })
+        .collect::<Vec<_>>();
+
+    Json(json!({ "object": "list", "model": request.get("model"), "data": data }))
+}
+
+fn pick_rule(state: &mut ServerState, request: &Value) -> Option<MockRule> {
+    let text = request
+        .get("messages")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|m| m.get("content").and_then(Value::as_str))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    for (index, rule) in state.script.rules.iter().enumerate() {
+        let exhausted = rule.times.is_some_and(|times| state.uses[index] >= times);
+        if !exhausted && state.patterns[index].is_match(&text) {
+            state.uses[index] += 1;
+            debug!("Mock LLM answering with rule {}", index);
+            return Some(rule.clone());
+        }
+    }
+
+    None
+}
+
+/// Server-sent events: content split on whitespace, tool calls in one delta.
+fn stream_reply(model: &Value, content: Option<String>, tool_calls: Vec<Value>) -> Response {
+    let chunk = |delta: Value| {
+        let event = json!({ "object": "chat.completion.chunk", "model": model, "choices": [{ "index": 0, "delta": delta }] });
+        format!("data: {}\n\n", event)
+    };
+
+    let mut body = chunk(json!({ "role": "assistant" }));
+    for piece in content
+        .unwrap_or_default()
+        .split_inclusive(char::is_whitespace)
+    {
+        body.push_str(&chunk(json!({ "content": piece })));
+    }
+    if !tool_calls.is_empty() {
+        let indexed = tool_calls
+            .into_iter()
+            .enumerate()
+            .map(|(index, mut call)| {
+                call["index"] = json!(index);
+                call
+            })
+            .collect::<Vec<_>>();
+        body.push_str(&chunk(json!({ "tool_calls": indexed })));
+    }
+    body.push_str("data: [DONE]\n\n");
+
+    ([(header::CONTENT_TYPE, "text/event-stream")], body).into_response()
+}
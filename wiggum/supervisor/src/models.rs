@@ -0,0 +1,96 @@
+use crate::agents::AgentType;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Ordered model preferences per agent role (state/models.json).
+///
+/// ```json
+/// { "default": ["qwen3-32b", "qwen3-8b"],
+///   "roles": { "ui_snob": ["qwen3-vl-32b", "qwen3-vl-8b"] },
+///   "context_lengths": { "qwen3-8b": 32768 } }
+/// ```
+///
+/// The first entry of a chain is the primary; the rest are fallbacks tried
+/// in order when the server does not list the ones before them. Context
+/// lengths override what the server reports (or fill in when it reports none).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelRoster {
+    #[serde(default)]
+    pub default: Vec<String>,
+    #[serde(default)]
+    pub roles: BTreeMap<AgentType, Vec<String>>,
+    #[serde(default)]
+    pub context_lengths: BTreeMap<String, usize>,
+}
+
+/// The model picked for a call and whether it came from further down the chain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelChoice {
+    pub model: String,
+    pub fallback: bool,
+}
+
+impl ModelRoster {
+    /// Load `models.json` if present, otherwise use `default_chain` for every role.
+    pub fn load(state_dir: &Path, default_chain: Vec<String>) -> Result<Self> {
+        let roster_path = state_dir.join("models.json");
+
+        let mut roster = if roster_path.exists() {
+            let content = fs::read_to_string(roster_path)?;
+            serde_json::from_str(&content)?
+        } else {
+            ModelRoster::default()
+        };
+
+        if roster.default.is_empty() {
+            roster.default = default_chain;
+        }
+
+        Ok(roster)
+    }
+
+    pub fn chain(&self, role: &AgentType) -> &[String] {
+        self.roles
+            .get(role)
+            .filter(|chain| !chain.is_empty())
+            .unwrap_or(&self.default)
+    }
+
+    /// Pick the first model of `role`'s chain that appears in `available`.
+    /// Falls back to the primary when nothing matches, since some servers
+    /// (LM Studio with JIT loading) serve models they do not list.
+    pub fn choose(&self, role: &AgentType, available: &[String]) -> Option<ModelChoice> {
+        let chain = self.chain(role);
+
+        chain
+            .iter()
+            .position(|wanted| available.iter().any(|served| model_matches(served, wanted)))
+            .map(|index| ModelChoice {
+                model: chain[index].clone(),
+                fallback: index > 0,
+            })
+            .or_else(|| {
+                chain.first().map(|primary| ModelChoice {
+                    model: primary.clone(),
+                    fallback: false,
+                })
+            })
+    }
+
+    /// Whether `role`'s primary model is among `available`.
+    pub fn primary_available(&self, role: &AgentType, available: &[String]) -> bool {
+        self.chain(role).first().is_some_and(|primary| {
+            available
+                .iter()
+                .any(|served| model_matches(served, primary))
+        })
+    }
+}
+
+/// Ollama lists `name:tag`; treat a bare name as `name:latest`.
+fn model_matches(served: &str, wanted: &str) -> bool {
+    served == wanted || served.strip_suffix(":latest") == Some(wanted)
+}
@@ -0,0 +1,669 @@
+use crate::metrics::{vue_scripts, Grammar};
+use crate::tools::SKIPPED_DIRS;
+use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use tree_sitter::{Node, Parser};
+use walkdir::WalkDir;
+
+/// Generated and vendored files are not part of the app's structure.
+const MAX_SOURCE_BYTES: u64 = 256 * 1024;
+
+/// Tried in order when an import leaves out the extension.
+const RESOLVE_EXTENSIONS: [&str; 9] = ["ts", "tsx", "js", "jsx", "mjs", "cjs", "mts", "cts", "vue"];
+
+/// What an import takes from its module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Imported {
+    /// Namespace imports, `require`, dynamic and side-effect imports, and
+    /// `export *`: any export may be used
+    Everything,
+    /// Export names, `default` for the default export
+    Names(Vec<String>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Import {
+    pub specifier: String,
+    pub line: u32,
+    pub imported: Imported,
+    /// The imported file relative to the workspace, when it is one
+    pub target: Option<String>,
+    /// A Rust `mod` item: where a module lives rather than what it uses
+    pub declaration: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Export {
+    /// `default` for the default export
+    pub name: String,
+    pub line: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Module {
+    /// Path relative to the workspace, with `/` separators
+    pub path: String,
+    pub imports: Vec<Import>,
+    pub exports: Vec<Export>,
+}
+
+/// The workspace's JS/TS/Vue and Rust modules and the imports between them.
+pub struct ModuleGraph {
+    pub root: PathBuf,
+    pub modules: BTreeMap<String, Module>,
+}
+
+impl ModuleGraph {
+    /// Parse every module in the workspace and resolve the imports that
+    /// point into it.
+    pub fn build(root: &Path) -> Result<Self> {
+        let walker = WalkDir::new(root)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|e| !SKIPPED_DIRS.contains(&e.file_name().to_string_lossy().as_ref()));
+
+        let mut graph = Self {
+            root: root.to_path_buf(),
+            modules: BTreeMap::new(),
+        };
+        for entry in walker
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+        {
+            let path = entry.path();
+            let Some(grammar) = Grammar::for_path(path) else {
+                continue;
+            };
+            let small = entry
+                .metadata()
+                .map(|m| m.len() <= MAX_SOURCE_BYTES)
+                .unwrap_or(false);
+            if !small || entry.file_name().to_string_lossy().contains(".min.") {
+                continue;
+            }
+            let Ok(source) = fs::read_to_string(path) else {
+                continue;
+            };
+
+            let relative = relative_path(root, path);
+            let module = parse_module(relative.clone(), &source, grammar)?;
+            graph.modules.insert(relative, module);
+        }
+
+        let resolved: Vec<(String, usize, Option<String>)> = graph
+            .modules
+            .values()
+            .flat_map(|module| {
+                module.imports.iter().enumerate().map(|(index, import)| {
+                    (
+                        module.path.clone(),
+                        index,
+                        graph.resolve_import(&module.path, &import.specifier),
+                    )
+                })
+            })
+            .collect();
+        for (path, index, target) in resolved {
+            if let Some(module) = graph.modules.get_mut(&path) {
+                module.imports[index].target = target;
+            }
+        }
+
+        Ok(graph)
+    }
+
+    /// The file `specifier` refers to from `from`, for relative imports,
+    /// the `@/` alias for a package's `src` directory and Rust module paths.
+    pub fn resolve_import(&self, from: &str, specifier: &str) -> Option<String> {
+        if from.ends_with(".rs") {
+            return self.resolve_rust(from, specifier);
+        }
+
+        let specifier = specifier.split(['?', '#']).next().unwrap_or(specifier);
+        let base = if specifier.starts_with("./") || specifier.starts_with("../") {
+            Path::new(from)
+                .parent()
+                .unwrap_or(Path::new(""))
+                .join(specifier)
+        } else if let Some(rest) = specifier.strip_prefix("@/") {
+            self.package_dir(from).join("src").join(rest)
+        } else {
+            return None;
+        };
+        self.resolve_path(&base)
+    }
+
+    /// The file a workspace-relative path names, trying the usual
+    /// extensions and index files when it has none.
+    pub fn resolve_path(&self, path: &Path) -> Option<String> {
+        let path = normalize(path);
+        let file = |candidate: &Path| {
+            self.root
+                .join(candidate)
+                .is_file()
+                .then(|| candidate.to_string_lossy().replace('\\', "/"))
+        };
+
+        if let Some(found) = file(&path) {
+            return Some(found);
+        }
+        // TypeScript sources import each other by their compiled `.js` name
+        if path.extension().is_some_and(|ext| ext == "js") {
+            if let Some(found) =
+                file(&path.with_extension("ts")).or_else(|| file(&path.with_extension("tsx")))
+            {
+                return Some(found);
+            }
+        }
+        RESOLVE_EXTENSIONS
+            .iter()
+            .find_map(|ext| file(Path::new(&format!("{}.{}", path.display(), ext))))
+            .or_else(|| {
+                RESOLVE_EXTENSIONS
+                    .iter()
+                    .find_map(|ext| file(&path.join(format!("index.{}", ext))))
+            })
+    }
+
+    /// The file of the Rust module a `use` path or `mod` item names: the
+    /// longest prefix of the path that is a file, or the module `crate`,
+    /// `self` or `super` stand for. Other crates resolve to nothing.
+    fn resolve_rust(&self, from: &str, specifier: &str) -> Option<String> {
+        let segments: Vec<&str> = specifier.split("::").collect();
+        let supers = segments.iter().take_while(|s| **s == "super").count();
+
+        let (mut dir, rest) = match segments[0] {
+            "crate" => (self.crate_src(from), &segments[1..]),
+            "self" => (rust_module_dir(from), &segments[1..]),
+            "super" => {
+                let mut dir = rust_module_dir(from);
+                for _ in 0..supers {
+                    dir.pop();
+                }
+                (dir, &segments[supers..])
+            }
+            // A child module, or another crate
+            _ => (rust_module_dir(from), &segments[..]),
+        };
+        let relative_start = !matches!(segments[0], "crate" | "self" | "super");
+
+        let mut target = None;
+        for segment in rest {
+            let file = |candidate: PathBuf| {
+                self.root
+                    .join(&candidate)
+                    .is_file()
+                    .then(|| candidate.to_string_lossy().replace('\\', "/"))
+            };
+            match file(dir.join(format!("{}.rs", segment)))
+                .or_else(|| file(dir.join(segment).join("mod.rs")))
+            {
+                Some(found) => {
+                    target = Some(found);
+                    dir.push(segment);
+                }
+                None => break,
+            }
+        }
+
+        if target.is_some() || relative_start {
+            return target;
+        }
+        // The path names an item of the module it starts from
+        let name = dir
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let candidates = [
+            dir.with_file_name(format!("{}.rs", name)),
+            dir.join("mod.rs"),
+            dir.join("lib.rs"),
+            dir.join("main.rs"),
+        ];
+        candidates
+            .iter()
+            .filter(|c| !c.as_os_str().is_empty())
+            .find(|c| self.root.join(c).is_file())
+            .map(|c| c.to_string_lossy().replace('\\', "/"))
+    }
+
+    /// `src` of the Cargo package `path` belongs to.
+    fn crate_src(&self, path: &str) -> PathBuf {
+        Path::new(path)
+            .ancestors()
+            .skip(1)
+            .find(|dir| self.root.join(dir).join("Cargo.toml").is_file())
+            .unwrap_or(Path::new(""))
+            .join("src")
+    }
+
+    /// Directory of the package.json or Cargo.toml nearest to `path`,
+    /// relative to the workspace; the workspace itself when there is none.
+    pub fn package_dir(&self, path: &str) -> PathBuf {
+        Path::new(path)
+            .ancestors()
+            .skip(1)
+            .find(|dir| {
+                ["package.json", "Cargo.toml"]
+                    .iter()
+                    .any(|m| self.root.join(dir).join(m).is_file())
+            })
+            .unwrap_or(Path::new(""))
+            .to_path_buf()
+    }
+}
+
+/// The npm package a bare specifier imports from, e.g. `@vue/router` for
+/// `@vue/router/dist/x.js`.
+pub fn package_name(specifier: &str) -> Option<&str> {
+    if specifier.starts_with('.')
+        || specifier.starts_with('/')
+        || specifier.starts_with("@/")
+        || specifier.starts_with('#')
+        || specifier.contains(':')
+    {
+        return None;
+    }
+    let segments = if specifier.starts_with('@') { 2 } else { 1 };
+    let end = specifier
+        .match_indices('/')
+        .nth(segments - 1)
+        .map(|(at, _)| at)
+        .unwrap_or(specifier.len());
+    Some(&specifier[..end])
+}
+
+pub(crate) fn relative_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// Directory holding the child modules of the Rust module in `path`:
+/// its own directory for `lib.rs`, `main.rs` and `mod.rs`, else a
+/// directory named after it.
+fn rust_module_dir(path: &str) -> PathBuf {
+    let path = Path::new(path);
+    let parent = path.parent().unwrap_or(Path::new("")).to_path_buf();
+    match path.file_name().and_then(|n| n.to_str()) {
+        Some("lib.rs" | "main.rs" | "mod.rs") => parent,
+        _ => parent.join(path.file_stem().unwrap_or_default()),
+    }
+}
+
+/// `path` with `.` and `..` components folded away.
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+fn parse_module(path: String, source: &str, grammar: Grammar) -> Result<Module> {
+    let mut module = Module {
+        path,
+        imports: Vec::new(),
+        exports: Vec::new(),
+    };
+
+    // A component's imports and exports live in its script blocks
+    if let Grammar::Rust = grammar {
+        let mut parser = Parser::new();
+        parser.set_language(&grammar.language())?;
+        let tree = parser
+            .parse(source, None)
+            .ok_or_else(|| anyhow!("could not parse {}", module.path))?;
+        collect_rust(tree.root_node(), source.as_bytes(), 0, &mut module);
+        return Ok(module);
+    }
+
+    let scripts = if module.path.ends_with(".vue") {
+        vue_scripts(source)
+            .into_iter()
+            .map(|(script, offset, typescript)| {
+                (
+                    script,
+                    offset,
+                    if typescript {
+                        Grammar::TypeScript
+                    } else {
+                        grammar
+                    },
+                )
+            })
+            .collect()
+    } else {
+        vec![(source, 0, grammar)]
+    };
+
+    for (code, line_offset, grammar) in scripts {
+        let mut parser = Parser::new();
+        parser.set_language(&grammar.language())?;
+        let tree = parser
+            .parse(code, None)
+            .ok_or_else(|| anyhow!("could not parse {}", module.path))?;
+        collect(tree.root_node(), code.as_bytes(), line_offset, &mut module);
+    }
+
+    Ok(module)
+}
+
+fn text<'a>(node: Node, source: &'a [u8]) -> &'a str {
+    node.utf8_text(source).unwrap_or_default()
+}
+
+fn string_value(node: Node, source: &[u8]) -> Option<String> {
+    matches!(node.kind(), "string" | "template_string")
+        .then(|| {
+            text(node, source)
+                .trim_matches(['"', '\'', '`'])
+                .to_string()
+        })
+        .filter(|value| !value.contains("${"))
+}
+
+fn collect(node: Node, source: &[u8], line_offset: u32, module: &mut Module) {
+    let line = node.start_position().row as u32 + 1 + line_offset;
+
+    match node.kind() {
+        "import_statement" => {
+            if let Some(specifier) = node
+                .child_by_field_name("source")
+                .and_then(|s| string_value(s, source))
+            {
+                module.imports.push(Import {
+                    specifier,
+                    line,
+                    imported: import_clause(node, source),
+                    target: None,
+                    declaration: false,
+                });
+            }
+            return;
+        }
+        "export_statement" => {
+            export_statement(node, source, line, module);
+        }
+        "call_expression" => {
+            let function = node.child_by_field_name("function");
+            let is_import = function.is_some_and(|f| {
+                f.kind() == "import" || (f.kind() == "identifier" && text(f, source) == "require")
+            });
+            let argument = node
+                .child_by_field_name("arguments")
+                .and_then(|a| a.named_child(0));
+            if let Some(specifier) = argument
+                .filter(|_| is_import)
+                .and_then(|a| string_value(a, source))
+            {
+                module.imports.push(Import {
+                    specifier,
+                    line,
+                    imported: Imported::Everything,
+                    target: None,
+                    declaration: false,
+                });
+            }
+        }
+        _ => {}
+    }
+
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        collect(child, source, line_offset, module);
+    }
+}
+
+fn import_clause(node: Node, source: &[u8]) -> Imported {
+    let mut cursor = node.walk();
+    let Some(clause) = node
+        .named_children(&mut cursor)
+        .find(|c| c.kind() == "import_clause")
+    else {
+        // `import './styles.css'` runs the module for its effects
+        return Imported::Everything;
+    };
+
+    let mut names = Vec::new();
+    let mut cursor = clause.walk();
+    for part in clause.named_children(&mut cursor) {
+        match part.kind() {
+            "identifier" => names.push("default".to_string()),
+            "namespace_import" => return Imported::Everything,
+            "named_imports" => {
+                let mut cursor = part.walk();
+                for specifier in part
+                    .named_children(&mut cursor)
+                    .filter(|s| s.kind() == "import_specifier")
+                {
+                    if let Some(name) = specifier.child_by_field_name("name") {
+                        names.push(text(name, source).trim_matches(['"', '\'']).to_string());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Imported::Names(names)
+}
+
+fn export_statement(node: Node, source: &[u8], line: u32, module: &mut Module) {
+    let mut export = |name: &str| {
+        module.exports.push(Export {
+            name: name.to_string(),
+            line,
+        })
+    };
+
+    let mut cursor = node.walk();
+    let children: Vec<Node> = node.children(&mut cursor).collect();
+
+    if children.iter().any(|c| c.kind() == "default") {
+        export("default");
+    } else if let Some(declaration) = node.child_by_field_name("declaration") {
+        match declaration.child_by_field_name("name") {
+            Some(name) => export(text(name, source)),
+            // `export const a = 1, { b } = c`
+            None => {
+                let mut cursor = declaration.walk();
+                for declarator in declaration
+                    .named_children(&mut cursor)
+                    .filter(|d| d.kind() == "variable_declarator")
+                {
+                    if let Some(name) = declarator.child_by_field_name("name") {
+                        let mut names = Vec::new();
+                        binding_names(name, source, &mut names);
+                        names.iter().for_each(|n| export(n));
+                    }
+                }
+            }
+        }
+    }
+
+    let mut reexported = Vec::new();
+    for child in &children {
+        match child.kind() {
+            "export_clause" => {
+                let mut cursor = child.walk();
+                for specifier in child
+                    .named_children(&mut cursor)
+                    .filter(|s| s.kind() == "export_specifier")
+                {
+                    let Some(name) = specifier.child_by_field_name("name") else {
+                        continue;
+                    };
+                    let name = text(name, source).trim_matches(['"', '\'']);
+                    let alias = specifier
+                        .child_by_field_name("alias")
+                        .map(|a| text(a, source).trim_matches(['"', '\'']));
+                    export(alias.unwrap_or(name));
+                    reexported.push(name.to_string());
+                }
+            }
+            "namespace_export" => {
+                if let Some(alias) = child.named_child(0) {
+                    export(text(alias, source));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // `export { a } from './a'` and `export * from './a'` also import
+    if let Some(specifier) = node
+        .child_by_field_name("source")
+        .and_then(|s| string_value(s, source))
+    {
+        let star = children
+            .iter()
+            .any(|c| c.kind() == "*" || c.kind() == "namespace_export");
+        let imported = if star {
+            Imported::Everything
+        } else {
+            Imported::Names(reexported)
+        };
+        module.imports.push(Import {
+            specifier,
+            line,
+            imported,
+            target: None,
+            declaration: false,
+        });
+    }
+}
+
+/// Names bound by a declarator's pattern, e.g. `a` and `c` in `{ a, b: c }`.
+fn binding_names(node: Node, source: &[u8], names: &mut Vec<String>) {
+    match node.kind() {
+        "identifier" | "shorthand_property_identifier_pattern" => {
+            names.push(text(node, source).to_string())
+        }
+        _ => {
+            let mut cursor = node.walk();
+            for child in node.named_children(&mut cursor) {
+                // In `{ b: c }` only the value is bound
+                if node.kind() == "pair_pattern" && node.child_by_field_name("key") == Some(child) {
+                    continue;
+                }
+                binding_names(child, source, names);
+            }
+        }
+    }
+}
+
+/// `use` declarations and file `mod` items. `depth` counts the inline
+/// modules around `node`, whose `super` still means this file.
+fn collect_rust(node: Node, source: &[u8], depth: usize, module: &mut Module) {
+    let line = node.start_position().row as u32 + 1;
+
+    match node.kind() {
+        "use_declaration" => {
+            let mut paths = Vec::new();
+            if let Some(argument) = node.child_by_field_name("argument") {
+                use_paths(argument, source, "", &mut paths);
+            }
+            for path in paths {
+                let mut specifier = path.as_str();
+                let mut stripped = 0;
+                while let Some(rest) = specifier
+                    .strip_prefix("super::")
+                    .filter(|_| stripped < depth)
+                {
+                    specifier = rest;
+                    stripped += 1;
+                }
+                let specifier = match stripped {
+                    0 => path.clone(),
+                    _ if specifier.starts_with("super::") => specifier.to_string(),
+                    _ => format!("self::{}", specifier),
+                };
+                module.imports.push(Import {
+                    specifier,
+                    line,
+                    imported: Imported::Everything,
+                    target: None,
+                    declaration: false,
+                });
+            }
+            return;
+        }
+        "mod_item" => match node.child_by_field_name("body") {
+            Some(body) => {
+                collect_rust(body, source, depth + 1, module);
+                return;
+            }
+            None => {
+                if let Some(name) = node.child_by_field_name("name") {
+                    let specifier = text(name, source).to_string();
+                    module.imports.push(Import {
+                        specifier,
+                        line,
+                        imported: Imported::Everything,
+                        target: None,
+                        declaration: true,
+                    });
+                }
+                return;
+            }
+        },
+        _ => {}
+    }
+
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        collect_rust(child, source, depth, module);
+    }
+}
+
+/// The paths a use tree brings in, e.g. `crate::a::b` and `crate::c` for
+/// `crate::{a::b, c::*}`.
+fn use_paths(node: Node, source: &[u8], prefix: &str, out: &mut Vec<String>) {
+    let join = |path: &str| {
+        if prefix.is_empty() {
+            path.to_string()
+        } else {
+            format!("{}::{}", prefix, path)
+        }
+    };
+
+    match node.kind() {
+        "use_as_clause" => {
+            if let Some(path) = node.child_by_field_name("path") {
+                use_paths(path, source, prefix, out);
+            }
+        }
+        "use_wildcard" => {
+            if let Some(path) = node.named_child(0) {
+                use_paths(path, source, prefix, out);
+            }
+        }
+        "scoped_use_list" => {
+            let path = node
+                .child_by_field_name("path")
+                .map(|p| join(text(p, source)))
+                .unwrap_or_else(|| prefix.to_string());
+            if let Some(list) = node.child_by_field_name("list") {
+                use_paths(list, source, &path, out);
+            }
+        }
+        "use_list" => {
+            let mut cursor = node.walk();
+            for item in node.named_children(&mut cursor) {
+                use_paths(item, source, prefix, out);
+            }
+        }
+        // `self` in a list is the prefix itself
+        "self" if !prefix.is_empty() => out.push(prefix.to_string()),
+        _ => out.push(join(text(node, source))),
+    }
+}
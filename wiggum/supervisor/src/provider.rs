@@ -0,0 +1,643 @@
+use crate::llm::{
+    ChatCompletionRequest, ChatMessage, Delta, FunctionCall, FunctionCallDelta, ToolCall,
+    ToolCallDelta, ToolDefinition,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// How a backend frames a streamed reply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamFormat {
+    /// Server-sent events with `data:` lines (OpenAI style)
+    Sse,
+    /// One JSON object per line (Ollama)
+    Ndjson,
+}
+
+/// One decoded unit of a streamed reply.
+#[derive(Debug)]
+pub enum StreamEvent {
+    Delta(Delta),
+    Done,
+    /// Keep-alives and chunks without a choice
+    Skip,
+}
+
+/// Wire format, health check and model listing of one kind of local
+/// inference server. `LlmClient` owns conversations, retries and the
+/// circuit breaker and delegates everything backend-specific here.
+#[async_trait]
+pub trait LlmProvider: Send + Sync + std::fmt::Debug {
+    fn name(&self) -> &'static str;
+
+    /// HTTP request for a chat completion, streamed or not.
+    fn chat_request(&self, http: &Client, request: &ChatCompletionRequest) -> RequestBuilder;
+
+    /// Assistant message from a non-streamed response body.
+    fn parse_reply(&self, body: &str) -> Result<ChatMessage>;
+
+    fn stream_format(&self) -> StreamFormat;
+
+    /// Decode one SSE data payload or NDJSON line.
+    fn parse_stream_event(&self, payload: &str) -> Result<StreamEvent>;
+
+    async fn is_healthy(&self, http: &Client) -> bool;
+
+    /// Identifiers of the models the server can serve right now.
+    async fn list_models(&self, http: &Client) -> Result<Vec<String>>;
+
+    /// Context window of `model` in tokens, if the server reports it.
+    async fn context_length(&self, http: &Client, model: &str) -> Option<usize>;
+
+    /// HTTP request embedding each of `input` with `model`.
+    fn embeddings_request(&self, http: &Client, model: &str, input: &[String]) -> RequestBuilder;
+
+    /// One vector per input, in input order.
+    fn parse_embeddings(&self, body: &str) -> Result<Vec<Vec<f32>>>;
+}
+
+/// Backends selectable from the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ProviderKind {
+    /// LM Studio, vLLM or any other OpenAI-compatible server
+    Openai,
+    Ollama,
+    Llamacpp,
+}
+
+impl ProviderKind {
+    pub fn default_url(self) -> &'static str {
+        match self {
+            ProviderKind::Openai => "http://localhost:1234/v1",
+            ProviderKind::Ollama => "http://localhost:11434",
+            ProviderKind::Llamacpp => "http://localhost:8080",
+        }
+    }
+
+    pub fn build(self, base_url: &str) -> Arc<dyn LlmProvider> {
+        match self {
+            ProviderKind::Openai => Arc::new(OpenAiCompatible::new(base_url)),
+            ProviderKind::Ollama => Arc::new(Ollama::new(base_url)),
+            ProviderKind::Llamacpp => Arc::new(LlamaCpp::new(base_url)),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatCompletionResponse {
+    choices: Vec<Choice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Choice {
+    message: ChatMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamChunk {
+    choices: Vec<StreamChoice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamChoice {
+    #[serde(default)]
+    delta: Delta,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingList {
+    data: Vec<EmbeddingEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingEntry {
+    #[serde(default)]
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelList {
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+fn parse_openai_reply(body: &str) -> Result<ChatMessage> {
+    let completion: ChatCompletionResponse = serde_json::from_str(body)?;
+    completion
+        .choices
+        .into_iter()
+        .next()
+        .map(|choice| choice.message)
+        .ok_or_else(|| anyhow!("No choices in LLM response"))
+}
+
+fn parse_openai_stream_event(payload: &str) -> Result<StreamEvent> {
+    if payload.trim() == "[DONE]" {
+        return Ok(StreamEvent::Done);
+    }
+
+    let chunk: StreamChunk = serde_json::from_str(payload)?;
+    Ok(chunk
+        .choices
+        .into_iter()
+        .next()
+        .map_or(StreamEvent::Skip, |choice| StreamEvent::Delta(choice.delta)))
+}
+
+async fn list_openai_models(http: &Client, url: &str) -> Result<Vec<String>> {
+    let models: ModelList = http
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(models.data.into_iter().map(|m| m.id).collect())
+}
+
+fn parse_openai_embeddings(body: &str) -> Result<Vec<Vec<f32>>> {
+    let mut embeddings: EmbeddingList = serde_json::from_str(body)?;
+    embeddings.data.sort_by_key(|e| e.index);
+    Ok(embeddings.data.into_iter().map(|e| e.embedding).collect())
+}
+
+/// Fields servers use for a model's window, loaded size before maximum:
+/// LM Studio, vLLM, OpenRouter-style and llama.cpp `meta`.
+const CONTEXT_FIELDS: &[&str] = &[
+    "/loaded_context_length",
+    "/max_context_length",
+    "/max_model_len",
+    "/context_length",
+    "/meta/n_ctx",
+    "/meta/n_ctx_train",
+];
+
+fn context_field(entry: &serde_json::Value) -> Option<usize> {
+    CONTEXT_FIELDS
+        .iter()
+        .find_map(|field| entry.pointer(field).and_then(|v| v.as_u64()))
+        .map(|n| n as usize)
+}
+
+async fn get_json(request: RequestBuilder) -> Option<serde_json::Value> {
+    request
+        .send()
+        .await
+        .ok()?
+        .error_for_status()
+        .ok()?
+        .json()
+        .await
+        .ok()
+}
+
+/// Look `model` up in an OpenAI-style `/models` listing.
+async fn openai_context_length(http: &Client, url: &str, model: &str) -> Option<usize> {
+    let models = get_json(http.get(url)).await?;
+    models
+        .get("data")?
+        .as_array()?
+        .iter()
+        .find(|entry| entry.get("id").and_then(|id| id.as_str()) == Some(model))
+        .and_then(context_field)
+}
+
+/// LM Studio, vLLM and other servers speaking the OpenAI chat API.
+/// `base_url` is the API root, e.g. `http://localhost:1234/v1`.
+#[derive(Debug)]
+pub struct OpenAiCompatible {
+    base_url: String,
+}
+
+impl OpenAiCompatible {
+    pub fn new(base_url: &str) -> Self {
+        // Accept the full completions endpoint too, as older configs used it
+        let base_url = base_url
+            .trim_end_matches('/')
+            .trim_end_matches("/chat/completions")
+            .to_string();
+        Self { base_url }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OpenAiCompatible {
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+
+    fn chat_request(&self, http: &Client, request: &ChatCompletionRequest) -> RequestBuilder {
+        http.post(format!("{}/chat/completions", self.base_url))
+            .json(request)
+    }
+
+    fn embeddings_request(&self, http: &Client, model: &str, input: &[String]) -> RequestBuilder {
+        http.post(format!("{}/embeddings", self.base_url))
+            .json(&serde_json::json!({ "model": model, "input": input }))
+    }
+
+    fn parse_embeddings(&self, body: &str) -> Result<Vec<Vec<f32>>> {
+        parse_openai_embeddings(body)
+    }
+
+    fn parse_reply(&self, body: &str) -> Result<ChatMessage> {
+        parse_openai_reply(body)
+    }
+
+    fn stream_format(&self) -> StreamFormat {
+        StreamFormat::Sse
+    }
+
+    fn parse_stream_event(&self, payload: &str) -> Result<StreamEvent> {
+        parse_openai_stream_event(payload)
+    }
+
+    async fn is_healthy(&self, http: &Client) -> bool {
+        http.get(format!("{}/models", self.base_url))
+            .send()
+            .await
+            .map(|r| r.status().is_success())
+            .unwrap_or(false)
+    }
+
+    async fn list_models(&self, http: &Client) -> Result<Vec<String>> {
+        list_openai_models(http, &format!("{}/models", self.base_url)).await
+    }
+
+    async fn context_length(&self, http: &Client, model: &str) -> Option<usize> {
+        if let Some(length) =
+            openai_context_length(http, &format!("{}/models", self.base_url), model).await
+        {
+            return Some(length);
+        }
+
+        // LM Studio only reports windows on its native REST API
+        let root = self.base_url.trim_end_matches("/v1");
+        get_json(http.get(format!("{}/api/v0/models/{}", root, model)))
+            .await
+            .as_ref()
+            .and_then(context_field)
+    }
+}
+
+/// llama.cpp's `llama-server`: OpenAI-compatible chat under `/v1`, but a
+/// dedicated `/health` endpoint that reports 503 while the model loads.
+#[derive(Debug)]
+pub struct LlamaCpp {
+    base_url: String,
+}
+
+impl LlamaCpp {
+    pub fn new(base_url: &str) -> Self {
+        let base_url = base_url
+            .trim_end_matches('/')
+            .trim_end_matches("/v1")
+            .to_string();
+        Self { base_url }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct LlamaCppHealth {
+    status: String,
+}
+
+#[async_trait]
+impl LlmProvider for LlamaCpp {
+    fn name(&self) -> &'static str {
+        "llamacpp"
+    }
+
+    fn chat_request(&self, http: &Client, request: &ChatCompletionRequest) -> RequestBuilder {
+        http.post(format!("{}/v1/chat/completions", self.base_url))
+            .json(request)
+    }
+
+    fn embeddings_request(&self, http: &Client, model: &str, input: &[String]) -> RequestBuilder {
+        http.post(format!("{}/v1/embeddings", self.base_url))
+            .json(&serde_json::json!({ "model": model, "input": input }))
+    }
+
+    fn parse_embeddings(&self, body: &str) -> Result<Vec<Vec<f32>>> {
+        parse_openai_embeddings(body)
+    }
+
+    fn parse_reply(&self, body: &str) -> Result<ChatMessage> {
+        parse_openai_reply(body)
+    }
+
+    fn stream_format(&self) -> StreamFormat {
+        StreamFormat::Sse
+    }
+
+    fn parse_stream_event(&self, payload: &str) -> Result<StreamEvent> {
+        parse_openai_stream_event(payload)
+    }
+
+    async fn is_healthy(&self, http: &Client) -> bool {
+        let Ok(response) = http.get(format!("{}/health", self.base_url)).send().await else {
+            return false;
+        };
+        if !response.status().is_success() {
+            return false;
+        }
+        response
+            .json::<LlamaCppHealth>()
+            .await
+            .map(|health| health.status == "ok")
+            .unwrap_or(false)
+    }
+
+    async fn list_models(&self, http: &Client) -> Result<Vec<String>> {
+        list_openai_models(http, &format!("{}/v1/models", self.base_url)).await
+    }
+
+    async fn context_length(&self, http: &Client, model: &str) -> Option<usize> {
+        // `/props` has the window the server was started with, which may be
+        // smaller than the one the model was trained on
+        let props = get_json(http.get(format!("{}/props", self.base_url))).await;
+        if let Some(n_ctx) = props
+            .as_ref()
+            .and_then(|p| p.pointer("/default_generation_settings/n_ctx"))
+            .and_then(|v| v.as_u64())
+        {
+            return Some(n_ctx as usize);
+        }
+        openai_context_length(http, &format!("{}/v1/models", self.base_url), model).await
+    }
+}
+
+/// Ollama's native API (`/api/chat`, `/api/tags`), which streams NDJSON
+/// and takes sampling settings under `options`.
+#[derive(Debug)]
+pub struct Ollama {
+    base_url: String,
+}
+
+impl Ollama {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct OllamaChatRequest<'a> {
+    model: &'a str,
+    messages: Vec<OllamaMessage>,
+    stream: bool,
+    options: OllamaOptions<'a>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<&'a Vec<ToolDefinition>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize)]
+struct OllamaOptions<'a> {
+    temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_predict: Option<u32>,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    stop: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OllamaMessage {
+    role: String,
+    #[serde(default)]
+    content: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<OllamaToolCall>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OllamaToolCall {
+    function: OllamaFunctionCall,
+}
+
+/// Ollama passes tool arguments as a JSON object, not an encoded string.
+#[derive(Debug, Serialize, Deserialize)]
+struct OllamaFunctionCall {
+    name: String,
+    #[serde(default)]
+    arguments: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaChatResponse {
+    message: Option<OllamaMessage>,
+    #[serde(default)]
+    done: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaEmbeddings {
+    embeddings: Vec<Vec<f32>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaTags {
+    models: Vec<OllamaModel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaModel {
+    name: String,
+}
+
+impl From<&ChatMessage> for OllamaMessage {
+    fn from(message: &ChatMessage) -> Self {
+        let tool_calls = message
+            .tool_calls
+            .iter()
+            .flatten()
+            .map(|call| OllamaToolCall {
+                function: OllamaFunctionCall {
+                    name: call.function.name.clone(),
+                    arguments: serde_json::from_str(&call.function.arguments)
+                        .unwrap_or(serde_json::Value::Null),
+                },
+            })
+            .collect();
+
+        Self {
+            role: message.role.clone(),
+            content: message.content.clone().unwrap_or_default(),
+            tool_calls,
+        }
+    }
+}
+
+impl OllamaMessage {
+    /// Ollama does not assign call ids, so number them for the tool loop.
+    fn into_chat_message(self) -> ChatMessage {
+        let tool_calls = self
+            .tool_calls
+            .into_iter()
+            .enumerate()
+            .map(|(index, call)| ToolCall {
+                id: format!("call_{}", index),
+                kind: "function".to_string(),
+                function: FunctionCall {
+                    name: call.function.name,
+                    arguments: call.function.arguments.to_string(),
+                },
+            })
+            .collect::<Vec<_>>();
+
+        ChatMessage {
+            role: self.role,
+            content: Some(self.content),
+            tool_calls: if tool_calls.is_empty() {
+                None
+            } else {
+                Some(tool_calls)
+            },
+            tool_call_id: None,
+        }
+    }
+}
+
+/// Translate an OpenAI `response_format` into Ollama's `format` field.
+fn ollama_format(response_format: &serde_json::Value) -> Option<serde_json::Value> {
+    match response_format.get("type").and_then(|t| t.as_str()) {
+        Some("json_schema") => response_format.pointer("/json_schema/schema").cloned(),
+        Some("json_object") => Some(serde_json::Value::String("json".to_string())),
+        _ => None,
+    }
+}
+
+#[async_trait]
+impl LlmProvider for Ollama {
+    fn name(&self) -> &'static str {
+        "ollama"
+    }
+
+    fn chat_request(&self, http: &Client, request: &ChatCompletionRequest) -> RequestBuilder {
+        let body = OllamaChatRequest {
+            model: &request.model,
+            messages: request.messages.iter().map(OllamaMessage::from).collect(),
+            stream: request.stream,
+            options: OllamaOptions {
+                temperature: request.params.temperature,
+                top_p: request.params.top_p,
+                num_predict: request.params.max_tokens,
+                stop: &request.params.stop,
+                seed: request.params.seed,
+            },
+            tools: request.tools.as_ref(),
+            format: request.response_format.as_ref().and_then(ollama_format),
+        };
+        http.post(format!("{}/api/chat", self.base_url)).json(&body)
+    }
+
+    fn embeddings_request(&self, http: &Client, model: &str, input: &[String]) -> RequestBuilder {
+        http.post(format!("{}/api/embed", self.base_url))
+            .json(&serde_json::json!({ "model": model, "input": input }))
+    }
+
+    fn parse_embeddings(&self, body: &str) -> Result<Vec<Vec<f32>>> {
+        let response: OllamaEmbeddings = serde_json::from_str(body)?;
+        Ok(response.embeddings)
+    }
+
+    fn parse_reply(&self, body: &str) -> Result<ChatMessage> {
+        let response: OllamaChatResponse = serde_json::from_str(body)?;
+        response
+            .message
+            .map(OllamaMessage::into_chat_message)
+            .ok_or_else(|| anyhow!("No message in Ollama response"))
+    }
+
+    fn stream_format(&self) -> StreamFormat {
+        StreamFormat::Ndjson
+    }
+
+    fn parse_stream_event(&self, payload: &str) -> Result<StreamEvent> {
+        let response: OllamaChatResponse = serde_json::from_str(payload)?;
+        let Some(message) = response.message else {
+            return Ok(if response.done {
+                StreamEvent::Done
+            } else {
+                StreamEvent::Skip
+            });
+        };
+
+        if response.done && message.content.is_empty() && message.tool_calls.is_empty() {
+            return Ok(StreamEvent::Done);
+        }
+
+        // Ollama sends each tool call whole, so one fragment carries everything
+        let tool_calls = message
+            .tool_calls
+            .into_iter()
+            .enumerate()
+            .map(|(index, call)| ToolCallDelta {
+                index,
+                id: Some(format!("call_{}", index)),
+                function: Some(FunctionCallDelta {
+                    name: Some(call.function.name),
+                    arguments: Some(call.function.arguments.to_string()),
+                }),
+            })
+            .collect::<Vec<_>>();
+
+        Ok(StreamEvent::Delta(Delta {
+            content: Some(message.content),
+            tool_calls: if tool_calls.is_empty() {
+                None
+            } else {
+                Some(tool_calls)
+            },
+        }))
+    }
+
+    async fn is_healthy(&self, http: &Client) -> bool {
+        http.get(format!("{}/api/version", self.base_url))
+            .send()
+            .await
+            .map(|r| r.status().is_success())
+            .unwrap_or(false)
+    }
+
+    async fn list_models(&self, http: &Client) -> Result<Vec<String>> {
+        let tags: OllamaTags = http
+            .get(format!("{}/api/tags", self.base_url))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(tags.models.into_iter().map(|m| m.name).collect())
+    }
+
+    async fn context_length(&self, http: &Client, model: &str) -> Option<usize> {
+        let show = get_json(
+            http.post(format!("{}/api/show", self.base_url))
+                .json(&serde_json::json!({ "model": model })),
+        )
+        .await?;
+
+        // Architecture-prefixed, e.g. `qwen3.context_length`
+        show.get("model_info")?
+            .as_object()?
+            .iter()
+            .find(|(key, _)| key.ends_with(".context_length"))
+            .and_then(|(_, value)| value.as_u64())
+            .map(|n| n as usize)
+    }
+}
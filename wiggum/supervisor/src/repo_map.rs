@@ -0,0 +1,329 @@
+use crate::tools::SKIPPED_DIRS;
+use anyhow::Result;
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use walkdir::WalkDir;
+
+/// Files larger than this are listed but not read.
+const MAX_INDEXED_BYTES: u64 = 256 * 1024;
+const MAX_EXCERPT_FILES: usize = 6;
+/// Lines of context around each matching line in an excerpt.
+const EXCERPT_CONTEXT: usize = 3;
+const MAX_EXCERPT_LINES: usize = 60;
+
+const SOURCE_EXTENSIONS: [&str; 10] = [
+    "js", "mjs", "cjs", "ts", "jsx", "tsx", "vue", "svelte", "py", "rs",
+];
+const SKIPPED_FILES: [&str; 4] = [
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "bun.lockb",
+];
+
+/// What the indexer found in one workspace file.
+#[derive(Debug, Clone)]
+pub struct FileEntry {
+    /// Path relative to the workspace, with `/` separators
+    pub path: String,
+    pub lines: usize,
+    pub symbols: Vec<String>,
+    /// HTTP routes declared in the file, e.g. `GET /api/todos`
+    pub routes: Vec<String>,
+    pub component: bool,
+}
+
+/// Compact overview of the generated app (file tree, exported symbols,
+/// routes, components and package scripts) so gate agents see the code
+/// they judge without spending tool calls on discovery.
+#[derive(Debug, Clone)]
+pub struct RepoMap {
+    root: PathBuf,
+    files: Vec<FileEntry>,
+    /// package.json path -> script name -> command
+    scripts: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+struct Patterns {
+    symbol: Regex,
+    route: Regex,
+    python_def: Regex,
+    rust_item: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| Patterns {
+        symbol: Regex::new(r"^\s*export\s+(?:default\s+)?(?:async\s+)?(?:function\*?|class|const|let|var|interface|type|enum)\s+([A-Za-z_$][\w$]*)|^\s*module\.exports\.([A-Za-z_$][\w$]*)\s*=|^\s*exports\.([A-Za-z_$][\w$]*)\s*=").unwrap(),
+        route: Regex::new(r#"\b(?:app|router|server|api|\w+Router)\.(get|post|put|patch|delete|all)\(\s*['"`]([^'"`]+)['"`]"#).unwrap(),
+        python_def: Regex::new(r"^(?:async\s+)?(?:def|class)\s+([A-Za-z]\w*)").unwrap(),
+        rust_item: Regex::new(r"^\s*pub\s+(?:async\s+)?(?:fn|struct|enum|trait|mod|const|type)\s+([A-Za-z_]\w*)").unwrap(),
+    })
+}
+
+impl RepoMap {
+    pub fn build(root: &Path) -> Result<Self> {
+        let mut files = Vec::new();
+        let mut scripts = BTreeMap::new();
+
+        let walker = WalkDir::new(root)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|e| !SKIPPED_DIRS.contains(&e.file_name().to_string_lossy().as_ref()));
+
+        for entry in walker
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+        {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if SKIPPED_FILES.contains(&name.as_str()) || name.starts_with('.') {
+                continue;
+            }
+
+            let relative = entry
+                .path()
+                .strip_prefix(root)
+                .unwrap_or(entry.path())
+                .to_string_lossy()
+                .replace('\\', "/");
+
+            let readable = entry
+                .metadata()
+                .map(|m| m.len() <= MAX_INDEXED_BYTES)
+                .unwrap_or(false);
+            let Some(content) = readable
+                .then(|| fs::read_to_string(entry.path()).ok())
+                .flatten()
+            else {
+                files.push(FileEntry {
+                    path: relative,
+                    lines: 0,
+                    symbols: Vec::new(),
+                    routes: Vec::new(),
+                    component: false,
+                });
+                continue;
+            };
+
+            if name == "package.json" {
+                if let Some(found) = package_scripts(&content) {
+                    scripts.insert(relative.clone(), found);
+                }
+            }
+
+            files.push(index_file(relative, &content));
+        }
+
+        Ok(Self {
+            root: root.to_path_buf(),
+            files,
+            scripts,
+        })
+    }
+
+    pub fn files(&self) -> &[FileEntry] {
+        &self.files
+    }
+
+    /// The map itself: one line per file with what it declares, then routes
+    /// and package scripts.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        for file in &self.files {
+            out.push_str(&file.path);
+            if file.lines > 0 {
+                out.push_str(&format!(" ({} lines)", file.lines));
+            }
+            if file.component {
+                out.push_str(" [component]");
+            }
+            if !file.symbols.is_empty() {
+                out.push_str(&format!(": {}", file.symbols.join(", ")));
+            }
+            out.push('\n');
+        }
+
+        let routes: Vec<String> = self
+            .files
+            .iter()
+            .flat_map(|f| f.routes.iter().map(move |r| format!("{} ({})", r, f.path)))
+            .collect();
+        if !routes.is_empty() {
+            out.push_str("\nRoutes:\n");
+            for route in routes {
+                out.push_str(&format!("- {}\n", route));
+            }
+        }
+
+        for (package, scripts) in &self.scripts {
+            out.push_str(&format!("\nScripts in {}:\n", package));
+            for (name, command) in scripts {
+                out.push_str(&format!("- {}: {}\n", name, command));
+            }
+        }
+
+        out
+    }
+
+    /// Excerpts of the files that best match `query` (usually the task
+    /// description): the lines mentioning its keywords, with some context.
+    pub fn excerpts(&self, query: &str) -> String {
+        let keywords = keywords(query);
+        if keywords.is_empty() {
+            return String::new();
+        }
+
+        let mut scored: Vec<(usize, &FileEntry, String)> = self
+            .files
+            .iter()
+            .filter(|f| f.lines > 0 && is_source(&f.path))
+            .filter_map(|file| {
+                let content = fs::read_to_string(self.root.join(&file.path)).ok()?;
+                let score = relevance(file, &content, &keywords);
+                (score > 0).then_some((score, file, content))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.path.cmp(&b.1.path)));
+
+        scored
+            .iter()
+            .take(MAX_EXCERPT_FILES)
+            .map(|(_, file, content)| format!("--- {}\n{}", file.path, excerpt(content, &keywords)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl FileEntry {
+    /// Whether the file is code the indexer understands.
+    pub fn is_source(&self) -> bool {
+        is_source(&self.path)
+    }
+}
+
+fn is_source(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .is_some_and(|ext| SOURCE_EXTENSIONS.contains(&ext.to_string_lossy().as_ref()))
+}
+
+fn index_file(path: String, content: &str) -> FileEntry {
+    let patterns = patterns();
+    let mut symbols = Vec::new();
+    let mut routes = Vec::new();
+
+    if is_source(&path) {
+        for line in content.lines() {
+            if let Some(captures) = patterns.symbol.captures(line) {
+                if let Some(name) = captures.iter().skip(1).flatten().next() {
+                    symbols.push(name.as_str().to_string());
+                }
+            }
+            if let Some(captures) = patterns
+                .python_def
+                .captures(line)
+                .filter(|_| path.ends_with(".py"))
+            {
+                symbols.push(captures[1].to_string());
+            }
+            if let Some(captures) = patterns
+                .rust_item
+                .captures(line)
+                .filter(|_| path.ends_with(".rs"))
+            {
+                symbols.push(captures[1].to_string());
+            }
+            for captures in patterns.route.captures_iter(line) {
+                routes.push(format!("{} {}", captures[1].to_uppercase(), &captures[2]));
+            }
+        }
+    }
+
+    let file_name = path.rsplit('/').next().unwrap_or(&path);
+    let component = path.ends_with(".vue")
+        || path.ends_with(".svelte")
+        || ((path.ends_with(".jsx") || path.ends_with(".tsx"))
+            && file_name.starts_with(|c: char| c.is_ascii_uppercase()));
+
+    symbols.dedup();
+    FileEntry {
+        lines: content.lines().count(),
+        path,
+        symbols,
+        routes,
+        component,
+    }
+}
+
+fn package_scripts(content: &str) -> Option<BTreeMap<String, String>> {
+    let package: serde_json::Value = serde_json::from_str(content).ok()?;
+    let scripts = package.get("scripts")?.as_object()?;
+    Some(
+        scripts
+            .iter()
+            .filter_map(|(name, command)| Some((name.clone(), command.as_str()?.to_string())))
+            .collect(),
+    )
+}
+
+/// Lowercased words of four letters or more, minus common filler.
+fn keywords(query: &str) -> BTreeSet<String> {
+    const STOP_WORDS: [&str; 12] = [
+        "that", "with", "this", "from", "have", "should", "must", "when", "user", "users", "able",
+        "each",
+    ];
+
+    query
+        .split(|c: char| !c.is_alphanumeric())
+        .map(str::to_lowercase)
+        .filter(|w| w.len() >= 4 && !STOP_WORDS.contains(&w.as_str()))
+        .collect()
+}
+
+/// Path hits count more than content hits; content hits are capped per
+/// keyword so one long file does not win on volume.
+fn relevance(file: &FileEntry, content: &str, keywords: &BTreeSet<String>) -> usize {
+    let path = file.path.to_lowercase();
+    let content = content.to_lowercase();
+
+    keywords
+        .iter()
+        .map(|word| {
+            let in_path = if path.contains(word.as_str()) { 5 } else { 0 };
+            in_path + content.matches(word.as_str()).count().min(5)
+        })
+        .sum()
+}
+
+fn excerpt(content: &str, keywords: &BTreeSet<String>) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut wanted = BTreeSet::new();
+
+    for (index, line) in lines.iter().enumerate() {
+        let lower = line.to_lowercase();
+        if keywords.iter().any(|k| lower.contains(k.as_str())) {
+            wanted.extend(
+                index.saturating_sub(EXCERPT_CONTEXT)
+                    ..(index + EXCERPT_CONTEXT + 1).min(lines.len()),
+            );
+        }
+        if wanted.len() >= MAX_EXCERPT_LINES {
+            break;
+        }
+    }
+
+    let mut out = String::new();
+    let mut previous = None;
+    for index in wanted.into_iter().take(MAX_EXCERPT_LINES) {
+        if previous.is_some_and(|p| p + 1 != index) {
+            out.push_str("...\n");
+        }
+        out.push_str(&format!("{:>4} {}\n", index + 1, lines[index]));
+        previous = Some(index);
+    }
+    out
+}
@@ -0,0 +1,133 @@
+use crate::llm::LlmError;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Exponential backoff with full jitter for transient LLM failures.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `attempt` (0-based): a random duration up
+    /// to `base_delay * 2^attempt`, capped at `max_delay`.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let ceiling = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        ceiling.mul_f64(rand::random::<f64>())
+    }
+}
+
+/// Whether an error from the LLM backend is worth retrying.
+///
+/// Connection failures, timeouts, rate limits and 5xx responses are
+/// transient (LM Studio refuses connections while a model loads); other
+/// 4xx responses and malformed bodies will fail the same way again.
+pub fn is_retryable(error: &anyhow::Error) -> bool {
+    if let Some(llm_error) = error.downcast_ref::<LlmError>() {
+        return match llm_error {
+            LlmError::Status { status, .. } => {
+                matches!(status, 408 | 425 | 429 | 500 | 502 | 503 | 504)
+            }
+            LlmError::Timeout(_) => true,
+            LlmError::BackendUnavailable | LlmError::CassetteMiss { .. } => false,
+        };
+    }
+
+    if let Some(http_error) = error.downcast_ref::<reqwest::Error>() {
+        return http_error.is_connect() || http_error.is_timeout() || http_error.is_request();
+    }
+
+    false
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+#[derive(Debug)]
+struct BreakerInner {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+}
+
+/// Stops hammering a backend that keeps failing. After `threshold`
+/// consecutive exhausted retries the breaker opens and calls fail fast;
+/// once `cooldown` has passed a health check decides whether to close it.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    inner: Mutex<BreakerInner>,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new(3, Duration::from_secs(30))
+    }
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold,
+            cooldown,
+            inner: Mutex::new(BreakerInner {
+                consecutive_failures: 0,
+                opened_at: None,
+            }),
+        }
+    }
+
+    pub fn state(&self) -> BreakerState {
+        let inner = self.inner.lock().unwrap();
+        match inner.opened_at {
+            None => BreakerState::Closed,
+            Some(opened_at) if opened_at.elapsed() >= self.cooldown => BreakerState::HalfOpen,
+            Some(_) => BreakerState::Open,
+        }
+    }
+
+    pub fn record_success(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.opened_at.is_some() {
+            info!("LLM backend recovered, closing circuit breaker");
+        }
+        inner.consecutive_failures = 0;
+        inner.opened_at = None;
+    }
+
+    pub fn record_failure(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.consecutive_failures += 1;
+
+        if inner.consecutive_failures >= self.threshold {
+            if inner.opened_at.is_none() {
+                warn!(
+                    "LLM backend failed {} times in a row, opening circuit breaker",
+                    inner.consecutive_failures
+                );
+            }
+            // Re-opening restarts the cooldown after a failed probe
+            inner.opened_at = Some(Instant::now());
+        }
+    }
+}
@@ -1,15 +1,54 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::path::{Path, PathBuf};
 use std::fs;
-use chrono::{DateTime, Utc};
-use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Intent {
     pub description: String,
+    /// Numbered acceptance criteria the intent is judged against
+    #[serde(default)]
+    pub criteria: Vec<Criterion>,
     pub created_at: DateTime<Utc>,
 }
 
+/// One acceptance criterion of the intent, e.g. `AC-2: completed todos can be cleared`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Criterion {
+    pub id: String,
+    pub text: String,
+}
+
+impl Intent {
+    /// The description followed by its criteria, for prompts.
+    pub fn render(&self) -> String {
+        let mut out = self.description.trim_end().to_string();
+        if !self.criteria.is_empty() {
+            out.push_str("\n\nAcceptance criteria:");
+            for criterion in &self.criteria {
+                out.push_str(&format!("\n- {}: {}", criterion.id, criterion.text));
+            }
+        }
+        out
+    }
+}
+
+/// The bulleted or numbered lines of an intent description, used as its
+/// criteria when none are given explicitly.
+pub fn criteria_from_description(description: &str) -> Vec<String> {
+    static ITEM: OnceLock<Regex> = OnceLock::new();
+    let item = ITEM.get_or_init(|| Regex::new(r"^\s*(?:[-*]|\d+[.)])\s+(.+)$").unwrap());
+
+    description
+        .lines()
+        .filter_map(|line| item.captures(line))
+        .map(|captures| captures[1].trim().to_string())
+        .collect()
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TaskStatus {
     Pending,
@@ -23,6 +62,9 @@ pub struct Task {
     pub id: String,
     pub description: String,
     pub status: TaskStatus,
+    /// Ids of the intent criteria this task delivers
+    #[serde(default)]
+    pub criteria: Vec<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -88,9 +130,19 @@ impl StateManager {
         Ok(())
     }
 
-    pub fn set_intent(&mut self, description: String) -> Result<()> {
+    /// Set the intent, numbering its criteria `AC-1`, `AC-2`, ... in order.
+    pub fn set_intent(&mut self, description: String, criteria: Vec<String>) -> Result<()> {
+        let criteria = criteria
+            .into_iter()
+            .enumerate()
+            .map(|(index, text)| Criterion {
+                id: format!("AC-{}", index + 1),
+                text,
+            })
+            .collect();
         self.intent = Some(Intent {
             description,
+            criteria,
             created_at: Utc::now(),
         });
         Ok(())
@@ -101,11 +153,17 @@ impl StateManager {
     }
 
     pub fn add_task(&mut self, description: String) -> Result<String> {
+        self.add_task_for(description, Vec::new())
+    }
+
+    /// Add a task that delivers the given intent criteria.
+    pub fn add_task_for(&mut self, description: String, criteria: Vec<String>) -> Result<String> {
         let id = format!("task_{}", self.tasks.tasks.len() + 1);
         let task = Task {
             id: id.clone(),
             description,
             status: TaskStatus::Pending,
+            criteria,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         };
@@ -125,16 +183,33 @@ impl StateManager {
     }
 
     pub fn get_pending_tasks(&self) -> Result<Vec<&Task>> {
-        Ok(self.tasks.tasks.iter()
+        Ok(self
+            .tasks
+            .tasks
+            .iter()
             .filter(|t| matches!(t.status, TaskStatus::Pending))
             .collect())
     }
 
     pub fn all_tasks_completed(&self) -> Result<bool> {
-        Ok(self.tasks.tasks.iter()
+        Ok(self
+            .tasks
+            .tasks
+            .iter()
             .all(|t| matches!(t.status, TaskStatus::Completed)))
     }
 
+    pub fn get_in_progress_task(&self) -> Option<&Task> {
+        self.tasks
+            .tasks
+            .iter()
+            .find(|t| matches!(t.status, TaskStatus::InProgress))
+    }
+
+    pub fn tasks(&self) -> &[Task] {
+        &self.tasks.tasks
+    }
+
     pub fn get_task(&self, task_id: &str) -> Option<&Task> {
         self.tasks.tasks.iter().find(|t| t.id == task_id)
     }
@@ -142,4 +217,20 @@ impl StateManager {
     pub fn has_tasks(&self) -> bool {
         !self.tasks.tasks.is_empty()
     }
-}
\ No newline at end of file
+
+    /// Where an agent's transcript for a task is kept.
+    pub fn conversation_path(&self, task_id: &str, agent: &str) -> PathBuf {
+        self.state_dir
+            .join("conversations")
+            .join(format!("{}-{}.json", task_id, agent))
+    }
+
+    /// The generated application lives next to the state directory.
+    pub fn workspace_dir(&self) -> PathBuf {
+        self.state_dir
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default()
+            .join("workspace")
+    }
+}
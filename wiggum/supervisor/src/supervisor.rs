@@ -1,17 +1,259 @@
 use crate::{
-    state::{StateManager, TaskStatus},
-    agents::{Agent, AgentType, AgentResult},
-    llm::LlmClient,
+    agents::{Agent, AgentResult, AgentType, GateSettings},
+    cassette::{Cassette, CassetteMode},
     cost::CostPressure,
+    llm::LlmClient,
+    models::{ModelChoice, ModelRoster},
+    provider::ProviderKind,
+    state::{self, StateManager, TaskStatus},
+    traceability::TraceabilityReport,
 };
-use anyhow::{anyhow, Result};
-use std::path::PathBuf;
-use tracing::{info, warn};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::process::Command;
+use tracing::{error, info, warn};
+
+/// Exit code the launcher treats as "all gates passed, leave the loop".
+pub const EXIT_APPROVED: i32 = 42;
+/// Exit code the launcher treats as "iteration cap reached, give up".
+pub const EXIT_EXHAUSTED: i32 = 43;
+
+pub const DEFAULT_TRUNK_MODEL: &str = "opencode/grok-code";
+pub const DEFAULT_AGENT_MODEL: &str = "local-model";
+pub const DEFAULT_MAX_ITERATIONS: u32 = 50;
+
+/// How long one tick waits for a downed LLM backend before giving up.
+const BACKEND_WAIT: Duration = Duration::from_secs(600);
 
 #[derive(Debug, Clone)]
 pub struct SupervisorConfig {
     pub state_dir: PathBuf,
-    pub lm_studio_url: String,
+    /// Which kind of inference server `llm_url` points at
+    pub llm_provider: ProviderKind,
+    /// API root of the inference server (e.g. LM Studio's http://localhost:1234/v1)
+    pub llm_url: String,
+    /// Model the verification agents talk to
+    pub agent_model: String,
+    /// Tried in order when `agent_model` is not loaded (state/models.json overrides per role)
+    pub fallback_models: Vec<String>,
+    /// Re-run gates that passed on a fallback model once the primary is back
+    pub rerun_fallback_gates: bool,
+    /// Record LLM traffic to, or replay it from, this file
+    pub cassette: Option<(PathBuf, CassetteMode)>,
+    /// Model for workspace retrieval embeddings; keyword excerpts are used without one
+    pub embedding_model: Option<String>,
+    /// Model handed to `opencode run` for the development phase
+    pub trunk_model: String,
+    /// Hard safety limit on development iterations
+    pub max_iterations: u32,
+    /// Shortest duplicated token run the slop gate reports
+    pub min_clone_tokens: usize,
+}
+
+/// One step of the trunk pipeline. Gates run in declaration order and the
+/// first failing gate sends the pipeline back to `Develop`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Phase {
+    Develop,
+    ExecutionVerification,
+    Exploration,
+    CodeSlop,
+    Architecture,
+    UiSnob,
+    Complete,
+    Exhausted,
+}
+
+impl Phase {
+    pub const GATES: [Phase; 5] = [
+        Phase::ExecutionVerification,
+        Phase::Exploration,
+        Phase::CodeSlop,
+        Phase::Architecture,
+        Phase::UiSnob,
+    ];
+
+    /// The agent that judges this phase, if it is a verification gate.
+    pub fn agent_type(self) -> Option<AgentType> {
+        match self {
+            Phase::ExecutionVerification => Some(AgentType::ExecutionVerification),
+            Phase::Exploration => Some(AgentType::ExploratoryTesting),
+            Phase::CodeSlop => Some(AgentType::CodeSlop),
+            Phase::Architecture => Some(AgentType::Architecture),
+            Phase::UiSnob => Some(AgentType::UiSnob),
+            Phase::Develop | Phase::Complete | Phase::Exhausted => None,
+        }
+    }
+
+    fn next(self) -> Phase {
+        match self {
+            Phase::Develop => Phase::ExecutionVerification,
+            Phase::ExecutionVerification => Phase::Exploration,
+            Phase::Exploration => Phase::CodeSlop,
+            Phase::CodeSlop => Phase::Architecture,
+            Phase::Architecture => Phase::UiSnob,
+            Phase::UiSnob | Phase::Complete => Phase::Complete,
+            Phase::Exhausted => Phase::Exhausted,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GateStatus {
+    NotRun,
+    Passed,
+    Failed(String),
+}
+
+#[derive(Debug, Clone)]
+pub enum PhaseOutcome {
+    Passed,
+    Failed(String),
+}
+
+impl From<AgentResult> for PhaseOutcome {
+    fn from(result: AgentResult) -> Self {
+        match result {
+            AgentResult::Success => PhaseOutcome::Passed,
+            AgentResult::Failure(reason) => PhaseOutcome::Failed(reason),
+        }
+    }
+}
+
+/// Persisted trunk state machine (state/pipeline.json).
+///
+/// Gate results survive the return to `Develop` so the improvement prompt
+/// can see what failed; they are cleared once development hands back to
+/// the first gate.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pipeline {
+    pub iteration: u32,
+    pub phase: Phase,
+    pub gates: BTreeMap<Phase, GateStatus>,
+    /// Model that produced each gate's latest verdict
+    #[serde(default)]
+    pub gate_models: BTreeMap<Phase, ModelChoice>,
+    /// Task the gates are currently judging
+    #[serde(default)]
+    pub task_id: Option<String>,
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self {
+            iteration: 0,
+            phase: Phase::Develop,
+            gates: Phase::GATES
+                .iter()
+                .map(|g| (*g, GateStatus::NotRun))
+                .collect(),
+            gate_models: BTreeMap::new(),
+            task_id: None,
+        }
+    }
+}
+
+impl Pipeline {
+    pub fn load(state_dir: &Path) -> Result<Self> {
+        let pipeline_path = state_dir.join("pipeline.json");
+
+        if pipeline_path.exists() {
+            let content = fs::read_to_string(pipeline_path)?;
+            Ok(serde_json::from_str(&content)?)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    pub fn save(&self, state_dir: &Path) -> Result<()> {
+        let pipeline_path = state_dir.join("pipeline.json");
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(pipeline_path, content)?;
+        Ok(())
+    }
+
+    /// Apply the outcome of the current phase and move to the next one.
+    pub fn advance(&mut self, outcome: PhaseOutcome, max_iterations: u32) -> Phase {
+        let current = self.phase;
+
+        match outcome {
+            PhaseOutcome::Passed => {
+                if current == Phase::Develop {
+                    for status in self.gates.values_mut() {
+                        *status = GateStatus::NotRun;
+                    }
+                    self.gate_models.clear();
+                } else if current.agent_type().is_some() {
+                    self.gates.insert(current, GateStatus::Passed);
+                }
+                self.phase = current.next();
+            }
+            PhaseOutcome::Failed(reason) => {
+                if current.agent_type().is_some() {
+                    self.gates.insert(current, GateStatus::Failed(reason));
+                }
+                self.iteration += 1;
+                self.phase = if self.iteration >= max_iterations {
+                    Phase::Exhausted
+                } else {
+                    Phase::Develop
+                };
+            }
+        }
+
+        self.phase
+    }
+
+    /// Start the next task from a clean `Develop` phase, keeping the iteration count.
+    pub fn restart(&mut self) {
+        self.phase = Phase::Develop;
+    }
+
+    /// Gates whose pass verdict came from a fallback model.
+    pub fn passed_on_fallback(&self) -> Vec<Phase> {
+        Phase::GATES
+            .iter()
+            .copied()
+            .filter(|g| self.gates.get(g) == Some(&GateStatus::Passed))
+            .filter(|g| {
+                self.gate_models
+                    .get(g)
+                    .is_some_and(|choice| choice.fallback)
+            })
+            .collect()
+    }
+
+    /// Send the pipeline back to re-judge `gates`, resuming at the earliest.
+    pub fn requeue(&mut self, gates: &[Phase]) {
+        for gate in gates {
+            self.gates.insert(*gate, GateStatus::NotRun);
+        }
+        if let Some(earliest) = gates.iter().min() {
+            self.phase = *earliest;
+        }
+    }
+
+    pub fn all_gates_passed(&self) -> bool {
+        Phase::GATES
+            .iter()
+            .all(|g| self.gates.get(g) == Some(&GateStatus::Passed))
+    }
+
+    /// Failures from the last gate run, formatted for the improvement prompt.
+    pub fn previous_issues(&self) -> String {
+        self.gates
+            .iter()
+            .filter_map(|(phase, status)| match status {
+                GateStatus::Failed(reason) => Some(format!("{:?}: {}", phase, reason)),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
 }
 
 pub struct Supervisor {
@@ -19,35 +261,71 @@ pub struct Supervisor {
     state: StateManager,
     llm_client: LlmClient,
     cost_pressure: CostPressure,
+    pipeline: Pipeline,
+    roster: ModelRoster,
 }
 
 impl Supervisor {
     pub async fn new(config: SupervisorConfig) -> Result<Self> {
         let state = StateManager::load(&config.state_dir)?;
-        let llm_client = LlmClient::new(&config.lm_studio_url)?;
+        let provider = config.llm_provider.build(&config.llm_url);
+        let mut llm_client = LlmClient::new(provider, &config.agent_model)?;
+        if let Some((path, mode)) = &config.cassette {
+            llm_client = llm_client.with_cassette(Cassette::open(path, *mode)?);
+        }
+        if let Some(model) = &config.embedding_model {
+            llm_client = llm_client.with_embedding_model(model);
+        }
         let cost_pressure = CostPressure::load(&config.state_dir)?;
+        let pipeline = Pipeline::load(&config.state_dir)?;
+
+        let mut default_chain = vec![config.agent_model.clone()];
+        default_chain.extend(config.fallback_models.iter().cloned());
+        let roster = ModelRoster::load(&config.state_dir, default_chain)?;
+        for (model, tokens) in &roster.context_lengths {
+            llm_client = llm_client.with_context_length(model, *tokens);
+        }
 
         Ok(Self {
             config,
             state,
             llm_client,
             cost_pressure,
+            pipeline,
+            roster,
         })
     }
 
-    pub async fn initialize(intent: String, config: SupervisorConfig) -> Result<()> {
+    /// Start a session. Without explicit `criteria`, the intent's bulleted
+    /// or numbered lines become its acceptance criteria.
+    pub async fn initialize(
+        intent: String,
+        criteria: Vec<String>,
+        config: SupervisorConfig,
+    ) -> Result<()> {
         // Create state directory if it doesn't exist
         std::fs::create_dir_all(&config.state_dir)?;
 
         // Initialize state with user intent
         let mut state_manager = StateManager::new(config.state_dir.clone());
-        state_manager.set_intent(intent)?;
+        let criteria = if criteria.is_empty() {
+            state::criteria_from_description(&intent)
+        } else {
+            criteria
+        };
+        if criteria.is_empty() {
+            warn!("Intent has no acceptance criteria; exit will not be traced to requirements");
+        }
+        state_manager.set_intent(intent, criteria)?;
         state_manager.save()?;
 
         // Initialize cost tracker
         let cost_pressure = CostPressure::new(config.state_dir.clone());
         cost_pressure.save()?;
 
+        // Fresh pipeline starts at the development phase
+        Pipeline::default().save(&config.state_dir)?;
+
         info!("Ralph Wiggum system initialized with user intent");
         Ok(())
     }
@@ -55,13 +333,38 @@ impl Supervisor {
     pub async fn tick(&mut self) -> Result<()> {
         info!("Starting supervisor tick");
 
+        // Gates need the LLM; pause rather than burn an iteration while it is down
+        if self.pipeline.phase.agent_type().is_some()
+            && !self.llm_client.wait_until_available(BACKEND_WAIT).await
+        {
+            warn!("LLM backend still unavailable, pausing without advancing the pipeline");
+            return Ok(());
+        }
+
         // Increment cost counter
         self.cost_pressure.increment_iteration();
 
+        if self.config.rerun_fallback_gates && self.pipeline.phase == Phase::Complete {
+            self.recheck_fallback_gates().await?;
+        }
+
         // Check if we should exit the loop
         if self.should_exit()? {
             info!("All verification gates passed. Requesting loop exit.");
-            std::process::exit(42);
+            std::process::exit(EXIT_APPROVED);
+        }
+
+        if self.pipeline.phase == Phase::Exhausted {
+            error!(
+                "Reached maximum iterations ({}). Manual intervention may be required.",
+                self.config.max_iterations
+            );
+            std::process::exit(EXIT_EXHAUSTED);
+        }
+
+        // A finished pipeline with open tasks moves on to the next task
+        if self.pipeline.phase == Phase::Complete {
+            self.pipeline.restart();
         }
 
         // Get next task to work on
@@ -69,18 +372,24 @@ impl Supervisor {
 
         match next_task {
             Some(task_id) => {
-                info!("Working on task: {}", task_id);
+                info!(
+                    "Working on task: {} (phase {:?}, iteration {})",
+                    task_id, self.pipeline.phase, self.pipeline.iteration
+                );
                 self.execute_task(task_id).await?;
             }
             None => {
                 // No tasks pending - this shouldn't happen if should_exit() is working correctly
-                warn!("No pending tasks but exit conditions not met. This indicates a logic error.");
+                warn!(
+                    "No pending tasks but exit conditions not met. This indicates a logic error."
+                );
             }
         }
 
         // Save state
         self.state.save()?;
         self.cost_pressure.save()?;
+        self.pipeline.save(&self.config.state_dir)?;
 
         Ok(())
     }
@@ -106,21 +415,67 @@ impl Supervisor {
             return Ok(false);
         }
 
+        // Every acceptance criterion needs passing evidence
+        if !self.criteria_traced()? {
+            return Ok(false);
+        }
+
         info!("All exit conditions met");
         Ok(true)
     }
 
+    fn gate_settings(&self) -> GateSettings {
+        GateSettings {
+            min_clone_tokens: self.config.min_clone_tokens,
+            ..GateSettings::default()
+        }
+    }
+
+    /// Whether the last traceability report shows passing evidence for
+    /// every criterion of the intent.
+    fn criteria_traced(&self) -> Result<bool> {
+        let Some(intent) = self.state.get_intent().filter(|i| !i.criteria.is_empty()) else {
+            return Ok(true);
+        };
+
+        let Some(report) = TraceabilityReport::load(&self.config.state_dir)? else {
+            info!(
+                "No traceability report yet for {} acceptance criteria",
+                intent.criteria.len()
+            );
+            return Ok(false);
+        };
+
+        let unmet = report.unmet();
+        let untraced = intent
+            .criteria
+            .iter()
+            .filter(|c| !report.criteria.iter().any(|t| t.id == c.id))
+            .count();
+        for criterion in &unmet {
+            info!(
+                "Criterion {} is {:?}: {}",
+                criterion.id, criterion.status, criterion.text
+            );
+        }
+        if untraced > 0 {
+            info!(
+                "{} criteria are missing from the traceability report",
+                untraced
+            );
+        }
+        Ok(unmet.is_empty() && untraced == 0)
+    }
+
     fn decide_next_task(&mut self) -> Result<Option<String>> {
+        // Resume the task the pipeline is already working on
+        if let Some(task) = self.state.get_in_progress_task() {
+            return Ok(Some(task.id.clone()));
+        }
+
         // If no tasks exist yet, create initial tasks from user intent
-        let pending_tasks = self.state.get_pending_tasks()?;
-        if pending_tasks.is_empty() && self.state.get_intent().is_some() {
+        if !self.state.has_tasks() && self.state.get_intent().is_some() {
             self.create_initial_tasks()?;
-            // Reload pending tasks after creation
-            let pending_tasks = self.state.get_pending_tasks()?;
-            if pending_tasks.is_empty() {
-                // If still no tasks, something went wrong
-                return Ok(None);
-            }
         }
 
         // Simple priority-based task selection
@@ -137,49 +492,299 @@ impl Supervisor {
 
     fn create_initial_tasks(&mut self) -> Result<()> {
         // TODO: Use LLM to decompose user intent into tasks
-        // For now, create a simple placeholder task
-        self.state.add_task("Implement basic application structure".to_string())?;
+        // For now, create a simple placeholder task delivering every criterion
+        let criteria = self
+            .state
+            .get_intent()
+            .map(|intent| intent.criteria.iter().map(|c| c.id.clone()).collect())
+            .unwrap_or_default();
+        self.state.add_task_for(
+            "Implement basic application structure".to_string(),
+            criteria,
+        )?;
         info!("Created initial tasks from user intent");
         Ok(())
     }
 
     async fn execute_task(&mut self, task_id: String) -> Result<()> {
-        info!("Executing task: {}", task_id);
+        let phase = self.pipeline.phase;
+        info!("Executing task {} in phase {:?}", task_id, phase);
 
         // Mark task as in progress
-        self.state.update_task_status(&task_id, TaskStatus::InProgress)?;
-
-        // Create and dispatch the appropriate agent
-        // For now, we'll start with the Execution Verification Agent
-        // TODO: Implement proper agent selection logic
+        self.state
+            .update_task_status(&task_id, TaskStatus::InProgress)?;
+        self.pipeline.task_id = Some(task_id.clone());
+
+        let outcome = match phase.agent_type() {
+            Some(agent_type) => {
+                let choice = self.choose_model(&agent_type).await;
+                if choice.fallback {
+                    warn!(
+                        "Primary model unavailable for {:?}, using fallback {}",
+                        agent_type, choice.model
+                    );
+                }
+
+                let agent = Agent::new(agent_type, self.llm_client.with_model(&choice.model))
+                    .with_settings(self.gate_settings());
+                let result = agent
+                    .execute(&task_id, &self.state, &self.cost_pressure)
+                    .await?;
+                self.pipeline.gate_models.insert(phase, choice);
+                result.into()
+            }
+            None if phase == Phase::Develop => self.develop().await?,
+            None => return Ok(()),
+        };
 
-        let agent = Agent::new(AgentType::ExecutionVerification, self.llm_client.clone());
-        let result = agent.execute(&task_id, &self.state).await?;
+        if let PhaseOutcome::Failed(reason) = &outcome {
+            warn!("Phase {:?} failed for task {}: {}", phase, task_id, reason);
+            self.cost_pressure.increment_failures();
+        }
 
-        match result {
-            AgentResult::Success => {
-                info!("Task {} completed successfully", task_id);
-                self.state.update_task_status(&task_id, TaskStatus::Completed)?;
+        match self.pipeline.advance(outcome, self.config.max_iterations) {
+            Phase::Complete => {
+                info!("Task {} passed every verification gate", task_id);
+                self.state
+                    .update_task_status(&task_id, TaskStatus::Completed)?;
             }
-            AgentResult::Failure(reason) => {
-                warn!("Task {} failed: {}", task_id, reason);
-                self.state.update_task_status(&task_id, TaskStatus::Pending)?;
-                // TODO: Feed failure back into the loop for retry
+            Phase::Exhausted => {
+                self.state
+                    .update_task_status(&task_id, TaskStatus::Failed)?;
             }
+            next => info!("Next phase: {:?}", next),
         }
 
         Ok(())
     }
 
+    /// First model of the role's chain that the server currently lists.
+    async fn choose_model(&self, role: &AgentType) -> ModelChoice {
+        let available = match self.llm_client.list_models().await {
+            Ok(models) => models,
+            Err(e) => {
+                warn!(
+                    "Could not list models ({}), assuming the primary is loaded",
+                    e
+                );
+                Vec::new()
+            }
+        };
+
+        self.roster
+            .choose(role, &available)
+            .unwrap_or_else(|| ModelChoice {
+                model: self.llm_client.model().to_string(),
+                fallback: false,
+            })
+    }
+
+    /// Before accepting a finished pipeline, re-judge gates that only passed
+    /// on a weaker fallback if their primary model is back.
+    async fn recheck_fallback_gates(&mut self) -> Result<()> {
+        let fallback_passes = self.pipeline.passed_on_fallback();
+        if fallback_passes.is_empty() {
+            return Ok(());
+        }
+
+        let available = match self.llm_client.list_models().await {
+            Ok(models) => models,
+            Err(_) => return Ok(()),
+        };
+
+        let recheck = fallback_passes
+            .into_iter()
+            .filter(|gate| {
+                gate.agent_type()
+                    .is_some_and(|role| self.roster.primary_available(&role, &available))
+            })
+            .collect::<Vec<_>>();
+        if recheck.is_empty() {
+            return Ok(());
+        }
+
+        info!(
+            "Primary models are back, re-running gates that passed on fallbacks: {:?}",
+            recheck
+        );
+        self.pipeline.requeue(&recheck);
+        if let Some(task_id) = self.pipeline.task_id.clone() {
+            self.state
+                .update_task_status(&task_id, TaskStatus::InProgress)?;
+        }
+
+        Ok(())
+    }
+
+    /// Phase 1: generate the application, or make targeted fixes for the
+    /// issues the gates reported last iteration.
+    async fn develop(&self) -> Result<PhaseOutcome> {
+        let intent = self
+            .state
+            .get_intent()
+            .ok_or_else(|| anyhow::anyhow!("No user intent found"))?;
+
+        let workspace_path = self.state.workspace_dir();
+        fs::create_dir_all(&workspace_path)?;
+
+        let generated =
+            workspace_path.join("client").is_dir() && workspace_path.join("server").is_dir();
+
+        let prompt = if !generated {
+            info!("Generating application based on user intent");
+            format!(
+                "You are a senior full-stack developer. The user wants: '{}'
+
+{}
+
+Create a full-stack application that perfectly satisfies this requirement:
+- server/: Node.js/Express REST API with SQLite persistence, proper error handling and middleware
+- client/: Vue 3 (Composition API) + Vite frontend with a modern, responsive design
+
+Both must work immediately with 'npm install && npm start' (server) and 'npm install && npm run dev' (client).
+Focus on creating exactly what the user requested.",
+                intent.description,
+                self.cost_pressure.get_cost_context()
+            )
+        } else {
+            info!("Application exists, requesting targeted improvements");
+            format!(
+                "You are the Ralph Wiggum Improvement Agent. The application exists but has verification failures.
+
+USER INTENT: {}
+ITERATION: {}
+
+{}
+
+Previous verification results:
+{}
+
+Fix the specific issues found by the verification agents. Do NOT regenerate everything from scratch.
+Keep all working code intact and make surgical, precise changes, highest priority issues first.",
+                intent.description,
+                self.pipeline.iteration,
+                self.cost_pressure.get_cost_context(),
+                self.pipeline.previous_issues()
+            )
+        };
+
+        let output = Command::new("opencode")
+            .arg("run")
+            .arg("--model")
+            .arg(&self.config.trunk_model)
+            .arg(&prompt)
+            .current_dir(&workspace_path)
+            .output()
+            .await?;
+
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            info!("[opencode] {}", line);
+        }
+
+        if output.status.success() {
+            Ok(PhaseOutcome::Passed)
+        } else {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            Ok(PhaseOutcome::Failed(format!(
+                "opencode exited with {}: {}",
+                output.status,
+                stderr.trim()
+            )))
+        }
+    }
+
     fn run_verification_gates(&self) -> Result<bool> {
-        // TODO: Implement all verification gates:
-        // - Execution Verification Agent
-        // - Code Slop Agent
-        // - Architecture Agent
-        // - UI Agent
+        // Gates are run one phase per tick; here we only check their recorded results
+        Ok(self.state.all_tasks_completed()? && self.pipeline.all_gates_passed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failed(reason: &str) -> PhaseOutcome {
+        PhaseOutcome::Failed(reason.to_string())
+    }
+
+    #[test]
+    fn gates_run_in_order_after_develop() {
+        let mut pipeline = Pipeline::default();
+        let mut visited = vec![pipeline.phase];
+        while pipeline.phase != Phase::Complete {
+            visited.push(pipeline.advance(PhaseOutcome::Passed, 10));
+        }
+
+        let mut expected = vec![Phase::Develop];
+        expected.extend(Phase::GATES);
+        expected.push(Phase::Complete);
+        assert_eq!(visited, expected);
+        assert!(pipeline.all_gates_passed());
+        assert_eq!(pipeline.iteration, 0);
+    }
+
+    #[test]
+    fn failed_gate_returns_to_develop() {
+        let mut pipeline = Pipeline::default();
+        pipeline.advance(PhaseOutcome::Passed, 10);
+        pipeline.advance(PhaseOutcome::Passed, 10);
+        assert_eq!(pipeline.phase, Phase::Exploration);
+
+        assert_eq!(pipeline.advance(failed("dead end"), 10), Phase::Develop);
+        assert_eq!(pipeline.iteration, 1);
+        assert_eq!(
+            pipeline.gates[&Phase::ExecutionVerification],
+            GateStatus::Passed
+        );
+        assert_eq!(
+            pipeline.gates[&Phase::Exploration],
+            GateStatus::Failed("dead end".to_string())
+        );
+        assert_eq!(pipeline.previous_issues(), "Exploration: dead end");
+    }
+
+    #[test]
+    fn iteration_cap_exhausts_the_pipeline() {
+        let mut pipeline = Pipeline::default();
+        for _ in 0..2 {
+            pipeline.advance(PhaseOutcome::Passed, 3);
+            assert_eq!(pipeline.advance(failed("broken"), 3), Phase::Develop);
+        }
 
-        // For now, just check if all tasks are completed
-        // This is a placeholder implementation
-        Ok(self.state.all_tasks_completed()?)
+        pipeline.advance(PhaseOutcome::Passed, 3);
+        assert_eq!(pipeline.advance(failed("broken"), 3), Phase::Exhausted);
+        assert_eq!(pipeline.iteration, 3);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn passing_develop_resets_the_gates() {
+        let mut pipeline = Pipeline::default();
+        pipeline.advance(PhaseOutcome::Passed, 10);
+        pipeline.advance(PhaseOutcome::Passed, 10);
+        pipeline.advance(failed("crash"), 10);
+        assert!(!pipeline.previous_issues().is_empty());
+
+        assert_eq!(
+            pipeline.advance(PhaseOutcome::Passed, 10),
+            Phase::ExecutionVerification
+        );
+        assert!(pipeline.gates.values().all(|g| *g == GateStatus::NotRun));
+        assert!(pipeline.previous_issues().is_empty());
+    }
+
+    #[test]
+    fn requeue_resumes_at_the_earliest_gate() {
+        let mut pipeline = Pipeline::default();
+        while pipeline.phase != Phase::Complete {
+            pipeline.advance(PhaseOutcome::Passed, 10);
+        }
+
+        pipeline.requeue(&[Phase::UiSnob, Phase::CodeSlop]);
+        assert_eq!(pipeline.phase, Phase::CodeSlop);
+        assert_eq!(pipeline.gates[&Phase::CodeSlop], GateStatus::NotRun);
+        assert_eq!(pipeline.gates[&Phase::Architecture], GateStatus::Passed);
+
+        pipeline.restart();
+        assert_eq!(pipeline.phase, Phase::Develop);
+    }
+}
@@ -0,0 +1,653 @@
+use crate::tools::SKIPPED_DIRS;
+use crate::verdict::{Finding, Severity};
+use anyhow::{anyhow, Result};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+use tracing::{info, warn};
+use walkdir::WalkDir;
+
+/// Default bound on one test suite run.
+pub const TEST_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Lines of a failure's stack kept in its finding.
+const STACK_EXCERPT_LINES: usize = 8;
+/// Lines of raw output kept when a runner produced no parsable results.
+const OUTPUT_TAIL_LINES: usize = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestRunnerKind {
+    Vitest,
+    Jest,
+    Bun,
+    Cargo,
+    Pytest,
+}
+
+impl TestRunnerKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            TestRunnerKind::Vitest => "vitest",
+            TestRunnerKind::Jest => "jest",
+            TestRunnerKind::Bun => "bun",
+            TestRunnerKind::Cargo => "cargo",
+            TestRunnerKind::Pytest => "pytest",
+        }
+    }
+
+    /// Command writing machine-readable results to `report`, where the
+    /// runner supports a report file.
+    fn command(self, report: &Path) -> Command {
+        let report = report.display().to_string();
+        let (program, args): (&str, Vec<String>) = match self {
+            TestRunnerKind::Vitest => (
+                "npx",
+                vec![
+                    "vitest".into(),
+                    "run".into(),
+                    "--reporter=junit".into(),
+                    format!("--outputFile={}", report),
+                ],
+            ),
+            TestRunnerKind::Jest => (
+                "npx",
+                vec![
+                    "jest".into(),
+                    "--ci".into(),
+                    "--json".into(),
+                    format!("--outputFile={}", report),
+                ],
+            ),
+            TestRunnerKind::Bun => (
+                "bun",
+                vec![
+                    "test".into(),
+                    "--reporter=junit".into(),
+                    format!("--reporter-outfile={}", report),
+                ],
+            ),
+            // libtest's JSON format is unstable; RUSTC_BOOTSTRAP unlocks it on stable
+            TestRunnerKind::Cargo => (
+                "cargo",
+                vec![
+                    "test".into(),
+                    "--no-fail-fast".into(),
+                    "--".into(),
+                    "-Z".into(),
+                    "unstable-options".into(),
+                    "--format".into(),
+                    "json".into(),
+                ],
+            ),
+            TestRunnerKind::Pytest => (
+                "python3",
+                vec![
+                    "-m".into(),
+                    "pytest".into(),
+                    "-q".into(),
+                    format!("--junitxml={}", report),
+                ],
+            ),
+        };
+
+        let mut command = Command::new(program);
+        command.args(args);
+        if self == TestRunnerKind::Cargo {
+            command.env("RUSTC_BOOTSTRAP", "1");
+        }
+        command
+    }
+}
+
+/// A test suite found in the workspace: which runner, run from where.
+#[derive(Debug, Clone)]
+pub struct TestSuite {
+    pub kind: TestRunnerKind,
+    pub dir: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+#[derive(Debug, Clone)]
+pub struct TestCase {
+    pub name: String,
+    pub file: Option<String>,
+    pub status: TestStatus,
+    pub message: Option<String>,
+    /// Stack trace or captured output of a failure
+    pub details: Option<String>,
+}
+
+/// Outcome of running one suite.
+#[derive(Debug, Clone)]
+pub struct TestRun {
+    pub kind: TestRunnerKind,
+    /// Suite directory relative to the workspace
+    pub dir: String,
+    pub cases: Vec<TestCase>,
+    pub success: bool,
+    pub timed_out: bool,
+    output_tail: String,
+}
+
+/// Find the test suites in a workspace: package.json files whose test
+/// setup names vitest, jest or bun, Cargo crates, and pytest projects.
+pub fn detect(workspace: &Path) -> Vec<TestSuite> {
+    let mut suites = Vec::new();
+
+    let walker = WalkDir::new(workspace)
+        .max_depth(3)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| !SKIPPED_DIRS.contains(&e.file_name().to_string_lossy().as_ref()));
+
+    for entry in walker
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        let Some(dir) = entry.path().parent() else {
+            continue;
+        };
+        let kind = match entry.file_name().to_string_lossy().as_ref() {
+            "package.json" => fs::read_to_string(entry.path())
+                .ok()
+                .and_then(|c| node_runner(&c)),
+            "Cargo.toml" => Some(TestRunnerKind::Cargo),
+            "pytest.ini" | "conftest.py" => Some(TestRunnerKind::Pytest),
+            "pyproject.toml" => fs::read_to_string(entry.path())
+                .ok()
+                .filter(|c| c.contains("[tool.pytest"))
+                .map(|_| TestRunnerKind::Pytest),
+            _ => None,
+        };
+
+        if let Some(kind) = kind {
+            let already = suites
+                .iter()
+                .any(|s: &TestSuite| s.kind == kind && dir.starts_with(&s.dir));
+            if !already {
+                suites.push(TestSuite {
+                    kind,
+                    dir: dir.to_path_buf(),
+                });
+            }
+        }
+    }
+
+    suites
+}
+
+fn node_runner(package_json: &str) -> Option<TestRunnerKind> {
+    let package: serde_json::Value = serde_json::from_str(package_json).ok()?;
+    let test_script = package
+        .pointer("/scripts/test")
+        .and_then(|s| s.as_str())
+        .unwrap_or_default();
+    let depends_on = |name: &str| {
+        ["dependencies", "devDependencies"]
+            .iter()
+            .any(|section| package.get(section).and_then(|d| d.get(name)).is_some())
+    };
+
+    if test_script.contains("vitest") || depends_on("vitest") {
+        Some(TestRunnerKind::Vitest)
+    } else if test_script.contains("jest") || depends_on("jest") {
+        Some(TestRunnerKind::Jest)
+    } else if test_script.contains("bun test") {
+        Some(TestRunnerKind::Bun)
+    } else {
+        None
+    }
+}
+
+/// Detect and run every suite in the workspace. A suite that cannot be
+/// started is reported as a failed run rather than aborting the rest.
+pub async fn run_all(workspace: &Path, timeout: Duration) -> Vec<TestRun> {
+    let mut runs = Vec::new();
+
+    for suite in detect(workspace) {
+        let node = matches!(suite.kind, TestRunnerKind::Vitest | TestRunnerKind::Jest);
+        let result = if node && !suite.dir.join("node_modules").is_dir() {
+            // npx would try to download the runner instead
+            Err(anyhow!(
+                "node_modules is missing; dependencies were never installed"
+            ))
+        } else {
+            suite.run(workspace, timeout).await
+        };
+
+        runs.push(result.unwrap_or_else(|e| {
+            TestRun {
+                kind: suite.kind,
+                dir: suite
+                    .dir
+                    .strip_prefix(workspace)
+                    .unwrap_or(&suite.dir)
+                    .display()
+                    .to_string(),
+                cases: Vec::new(),
+                success: false,
+                timed_out: false,
+                output_tail: format!("could not run tests: {}", e),
+            }
+        }));
+    }
+
+    runs
+}
+
+impl TestSuite {
+    /// Run the suite, killing it after `timeout`, and parse its results.
+    pub async fn run(&self, workspace: &Path, timeout: Duration) -> Result<TestRun> {
+        let dir = self
+            .dir
+            .strip_prefix(workspace)
+            .unwrap_or(&self.dir)
+            .display()
+            .to_string();
+        let report = std::env::temp_dir().join(format!(
+            "ralph-tests-{}-{}",
+            self.kind.name(),
+            uuid::Uuid::new_v4()
+        ));
+        info!(
+            "Running {} tests in {}",
+            self.kind.name(),
+            if dir.is_empty() { "." } else { &dir }
+        );
+
+        let child = self
+            .kind
+            .command(&report)
+            .current_dir(&self.dir)
+            .env("CI", "1")
+            .stdin(Stdio::null())
+            .kill_on_drop(true)
+            .output();
+
+        let output = match tokio::time::timeout(timeout, child).await {
+            Ok(output) => output?,
+            Err(_) => {
+                let _ = fs::remove_file(&report);
+                return Ok(TestRun {
+                    kind: self.kind,
+                    dir,
+                    cases: Vec::new(),
+                    success: false,
+                    timed_out: true,
+                    output_tail: String::new(),
+                });
+            }
+        };
+
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        let report_content = fs::read_to_string(&report).ok();
+        let _ = fs::remove_file(&report);
+
+        let parsed = match (self.kind, report_content) {
+            (TestRunnerKind::Jest, Some(json)) => parse_jest_json(&json),
+            (TestRunnerKind::Cargo, _) => Ok(parse_libtest_json(&stdout)),
+            (_, Some(xml)) => parse_junit(&xml),
+            // No report file: the test script may still have printed TAP
+            (_, None) => Ok(parse_tap(&stdout)),
+        };
+        let mut cases = parsed.unwrap_or_else(|e| {
+            warn!("Could not parse {} results: {}", self.kind.name(), e);
+            Vec::new()
+        });
+        // Jest reports absolute paths
+        for case in &mut cases {
+            if let Some(relative) = case
+                .file
+                .as_deref()
+                .and_then(|f| Path::new(f).strip_prefix(&self.dir).ok())
+            {
+                case.file = Some(relative.display().to_string());
+            }
+        }
+
+        Ok(TestRun {
+            kind: self.kind,
+            dir,
+            cases,
+            success: output.status.success(),
+            timed_out: false,
+            output_tail: tail(&format!("{}\n{}", stdout, stderr), OUTPUT_TAIL_LINES),
+        })
+    }
+}
+
+impl TestRun {
+    pub fn count(&self, status: TestStatus) -> usize {
+        self.cases.iter().filter(|c| c.status == status).count()
+    }
+
+    pub fn summary(&self) -> String {
+        let location = if self.dir.is_empty() {
+            ".".to_string()
+        } else {
+            self.dir.clone()
+        };
+        if self.timed_out {
+            return format!("{} in {}: timed out", self.kind.name(), location);
+        }
+        format!(
+            "{} in {}: {} passed, {} failed, {} skipped",
+            self.kind.name(),
+            location,
+            self.count(TestStatus::Passed),
+            self.count(TestStatus::Failed),
+            self.count(TestStatus::Skipped)
+        )
+    }
+
+    /// One finding per failing test, or one for a run that timed out or
+    /// failed without reporting any test.
+    pub fn findings(&self) -> Vec<Finding> {
+        let rule = format!("test:{}", self.kind.name());
+
+        if self.timed_out {
+            return vec![Finding::new(
+                Severity::Critical,
+                format!("{} did not finish within the time limit", self.summary()),
+            )
+            .rule(rule)];
+        }
+
+        let failed: Vec<&TestCase> = self
+            .cases
+            .iter()
+            .filter(|c| c.status == TestStatus::Failed)
+            .collect();
+        if failed.is_empty() && !self.success {
+            let message = format!(
+                "{} failed without reporting test results:\n{}",
+                self.summary(),
+                self.output_tail
+            );
+            return vec![Finding::new(Severity::Major, message).rule(rule)];
+        }
+
+        failed
+            .into_iter()
+            .map(|case| {
+                let mut message = format!("Test failed: {}", case.name);
+                if let Some(reason) = &case.message {
+                    message.push_str(&format!(" - {}", reason.lines().next().unwrap_or_default()));
+                }
+                if let Some(details) = &case.details {
+                    message.push('\n');
+                    message.push_str(
+                        &details
+                            .lines()
+                            .take(STACK_EXCERPT_LINES)
+                            .collect::<Vec<_>>()
+                            .join("\n"),
+                    );
+                }
+
+                let finding = Finding::new(Severity::Major, message).rule(rule.clone());
+                match &case.file {
+                    Some(file) => {
+                        finding.at(Path::new(&self.dir).join(file).display().to_string(), None)
+                    }
+                    None => finding,
+                }
+            })
+            .collect()
+    }
+}
+
+fn tail(text: &str, lines: usize) -> String {
+    let all: Vec<&str> = text.trim_end().lines().collect();
+    all[all.len().saturating_sub(lines)..].join("\n")
+}
+
+fn attribute(element: &BytesStart, name: &str) -> Option<String> {
+    element
+        .attributes()
+        .filter_map(|a| a.ok())
+        .find(|a| a.key.as_ref() == name.as_bytes())
+        .and_then(|a| a.unescape_value().ok())
+        .map(|v| v.to_string())
+}
+
+/// JUnit XML as written by vitest, bun and pytest.
+pub fn parse_junit(xml: &str) -> Result<Vec<TestCase>> {
+    let mut reader = Reader::from_str(xml);
+    let mut cases = Vec::new();
+    let mut suite_file: Option<String> = None;
+    let mut current: Option<TestCase> = None;
+    let mut in_failure = false;
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e) if e.name().as_ref() == b"testsuite" => {
+                suite_file = attribute(&e, "file")
+                    .or_else(|| attribute(&e, "name").filter(|n| n.contains('.')));
+            }
+            Event::Start(e) if e.name().as_ref() == b"testcase" => {
+                current = Some(junit_case(&e, &suite_file));
+            }
+            Event::Empty(e) if e.name().as_ref() == b"testcase" => {
+                cases.push(junit_case(&e, &suite_file));
+            }
+            Event::Start(e) | Event::Empty(e)
+                if matches!(e.name().as_ref(), b"failure" | b"error") =>
+            {
+                if let Some(case) = current.as_mut() {
+                    case.status = TestStatus::Failed;
+                    case.message = attribute(&e, "message").or_else(|| attribute(&e, "type"));
+                    in_failure = true;
+                }
+            }
+            Event::Start(e) | Event::Empty(e) if e.name().as_ref() == b"skipped" => {
+                if let Some(case) = current.as_mut() {
+                    case.status = TestStatus::Skipped;
+                }
+            }
+            Event::Text(text) if in_failure => {
+                if let Some(case) = current.as_mut() {
+                    let text = text.unescape()?.trim().to_string();
+                    if !text.is_empty() {
+                        case.details = Some(text);
+                    }
+                }
+            }
+            Event::CData(text) if in_failure => {
+                if let Some(case) = current.as_mut() {
+                    case.details = Some(String::from_utf8_lossy(&text).trim().to_string());
+                }
+            }
+            Event::End(e) if matches!(e.name().as_ref(), b"failure" | b"error") => {
+                in_failure = false
+            }
+            Event::End(e) if e.name().as_ref() == b"testcase" => {
+                cases.extend(current.take());
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(cases)
+}
+
+fn junit_case(element: &BytesStart, suite_file: &Option<String>) -> TestCase {
+    let name = attribute(element, "name").unwrap_or_default();
+    // vitest puts the file in `classname`, which `file` already carries
+    let class = attribute(element, "classname")
+        .filter(|c| !c.is_empty() && *c != name && Some(c) != suite_file.as_ref());
+
+    TestCase {
+        name: match class {
+            Some(class) => format!("{} > {}", class, name),
+            None => name,
+        },
+        file: attribute(element, "file").or_else(|| suite_file.clone()),
+        status: TestStatus::Passed,
+        message: None,
+        details: None,
+    }
+}
+
+/// Jest's `--json` report.
+pub fn parse_jest_json(json: &str) -> Result<Vec<TestCase>> {
+    let report: serde_json::Value = serde_json::from_str(json)?;
+    let files = report
+        .get("testResults")
+        .and_then(|r| r.as_array())
+        .ok_or_else(|| anyhow!("no testResults in jest report"))?;
+
+    let mut cases = Vec::new();
+    for file in files {
+        let path = file
+            .get("name")
+            .and_then(|n| n.as_str())
+            .map(str::to_string);
+        for assertion in file
+            .get("assertionResults")
+            .and_then(|a| a.as_array())
+            .into_iter()
+            .flatten()
+        {
+            let status = match assertion.get("status").and_then(|s| s.as_str()) {
+                Some("passed") => TestStatus::Passed,
+                Some("failed") => TestStatus::Failed,
+                _ => TestStatus::Skipped,
+            };
+            let failure = assertion
+                .get("failureMessages")
+                .and_then(|m| m.as_array())
+                .and_then(|m| m.first())
+                .and_then(|m| m.as_str())
+                .map(str::to_string);
+
+            cases.push(TestCase {
+                name: assertion
+                    .get("fullName")
+                    .and_then(|n| n.as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+                file: path.clone(),
+                status,
+                message: failure
+                    .as_ref()
+                    .and_then(|f| f.lines().next())
+                    .map(str::to_string),
+                details: failure,
+            });
+        }
+    }
+
+    Ok(cases)
+}
+
+/// libtest's JSON lines (`cargo test -- --format json`).
+pub fn parse_libtest_json(output: &str) -> Vec<TestCase> {
+    output
+        .lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .filter(|event| event.get("type").and_then(|t| t.as_str()) == Some("test"))
+        .filter_map(|event| {
+            let status = match event.get("event").and_then(|e| e.as_str())? {
+                "ok" => TestStatus::Passed,
+                "failed" | "timeout" => TestStatus::Failed,
+                "ignored" => TestStatus::Skipped,
+                _ => return None, // "started"
+            };
+            let stdout = event
+                .get("stdout")
+                .and_then(|s| s.as_str())
+                .map(str::to_string);
+
+            Some(TestCase {
+                name: event
+                    .get("name")
+                    .and_then(|n| n.as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+                file: None,
+                status,
+                message: stdout
+                    .as_ref()
+                    .and_then(|s| s.lines().find(|l| l.contains("panicked")))
+                    .map(str::to_string),
+                details: stdout,
+            })
+        })
+        .collect()
+}
+
+/// TAP 13/14: `ok`/`not ok` lines, with an optional indented YAML block
+/// after a failure carrying its message and stack.
+pub fn parse_tap(output: &str) -> Vec<TestCase> {
+    let mut cases: Vec<TestCase> = Vec::new();
+    let mut in_yaml = false;
+    let mut yaml = String::new();
+
+    for line in output.lines() {
+        let trimmed = line.trim();
+
+        if in_yaml {
+            if trimmed == "..." {
+                in_yaml = false;
+                if let Some(case) = cases.last_mut() {
+                    case.message = yaml
+                        .lines()
+                        .find_map(|l| l.trim().strip_prefix("message:"))
+                        .map(|m| m.trim().trim_matches(|c| c == '\'' || c == '"').to_string());
+                    case.details = Some(std::mem::take(&mut yaml));
+                }
+            } else {
+                yaml.push_str(trimmed);
+                yaml.push('\n');
+            }
+            continue;
+        }
+
+        if trimmed == "---" && cases.last().is_some_and(|c| c.status == TestStatus::Failed) {
+            in_yaml = true;
+            continue;
+        }
+
+        let (status, rest) = if let Some(rest) = trimmed.strip_prefix("not ok") {
+            (TestStatus::Failed, rest)
+        } else if let Some(rest) = trimmed.strip_prefix("ok") {
+            (TestStatus::Passed, rest)
+        } else {
+            continue;
+        };
+
+        // "ok 3 - name # SKIP reason"
+        let rest = rest.trim_start_matches(|c: char| c.is_ascii_digit() || c == ' ');
+        let rest = rest.strip_prefix("- ").unwrap_or(rest);
+        let (name, directive) = rest.split_once(" # ").unwrap_or((rest, ""));
+        let directive = directive.to_ascii_uppercase();
+        let status = if directive.starts_with("SKIP") || directive.starts_with("TODO") {
+            TestStatus::Skipped
+        } else {
+            status
+        };
+
+        cases.push(TestCase {
+            name: name.trim().to_string(),
+            file: None,
+            status,
+            message: None,
+            details: None,
+        });
+    }
+
+    cases
+}
@@ -0,0 +1,409 @@
+use crate::llm::{ToolCall, ToolDefinition};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use regex::Regex;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::json;
+use std::path::{Component, Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+use tracing::{info, warn};
+use walkdir::WalkDir;
+
+/// Tool output is fed back into the model's context, so keep it bounded.
+const MAX_OUTPUT_BYTES: usize = 16 * 1024;
+const MAX_GREP_MATCHES: usize = 200;
+const DEFAULT_COMMAND_TIMEOUT_SECS: u64 = 120;
+/// Longest a model may ask one command to run.
+const MAX_COMMAND_TIMEOUT_SECS: u64 = 600;
+
+/// Directories no agent needs to look inside.
+pub(crate) const SKIPPED_DIRS: [&str; 4] = ["node_modules", ".git", "target", "dist"];
+
+/// Function-calling tools offered to a model in an agent's tool loop.
+#[async_trait]
+pub trait ToolSet: Sync {
+    fn definitions(&self) -> Vec<ToolDefinition>;
+
+    /// Run one call; failures come back as text for the model to read.
+    async fn execute(&self, call: &ToolCall) -> String;
+}
+
+/// Function-calling tools an agent can use against the generated app.
+/// File tools resolve every path inside the workspace and HTTP is limited
+/// to localhost, where the app under test runs. `run_command` is a plain
+/// shell in the workspace root: bounded in time, not sandboxed.
+pub struct WorkspaceTools {
+    root: PathBuf,
+    http: Client,
+}
+
+#[derive(Deserialize)]
+struct PathArgs {
+    #[serde(default)]
+    path: String,
+}
+
+#[derive(Deserialize)]
+struct GrepArgs {
+    pattern: String,
+    #[serde(default)]
+    path: String,
+}
+
+#[derive(Deserialize)]
+struct CommandArgs {
+    command: String,
+    timeout_secs: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct HttpArgs {
+    method: String,
+    url: String,
+    body: Option<String>,
+}
+
+impl WorkspaceTools {
+    pub fn new(root: PathBuf) -> Result<Self> {
+        let http = Client::builder().timeout(Duration::from_secs(30)).build()?;
+
+        Ok(Self { root, http })
+    }
+
+    pub fn definitions(&self) -> Vec<ToolDefinition> {
+        vec![
+            ToolDefinition::function(
+                "read_file",
+                "Read a text file from the workspace",
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "path": { "type": "string", "description": "Path relative to the workspace root" }
+                    },
+                    "required": ["path"]
+                }),
+            ),
+            ToolDefinition::function(
+                "list_dir",
+                "List the entries of a workspace directory",
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "path": { "type": "string", "description": "Directory relative to the workspace root; empty for the root" }
+                    }
+                }),
+            ),
+            ToolDefinition::function(
+                "grep",
+                "Search workspace files for a regular expression",
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "pattern": { "type": "string", "description": "Regular expression" },
+                        "path": { "type": "string", "description": "File or directory to search; empty for the whole workspace" }
+                    },
+                    "required": ["pattern"]
+                }),
+            ),
+            ToolDefinition::function(
+                "run_command",
+                "Run a shell command in the workspace root and return its exit code and output",
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "command": { "type": "string" },
+                        "timeout_secs": { "type": "integer", "description": "Defaults to 120, at most 600" }
+                    },
+                    "required": ["command"]
+                }),
+            ),
+            ToolDefinition::function(
+                "http_request",
+                "Send an HTTP request to the app running on localhost",
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "method": { "type": "string", "enum": ["GET", "POST", "PUT", "PATCH", "DELETE"] },
+                        "url": { "type": "string", "description": "Must point at localhost or 127.0.0.1" },
+                        "body": { "type": "string", "description": "JSON request body" }
+                    },
+                    "required": ["method", "url"]
+                }),
+            ),
+        ]
+    }
+
+    /// Run a tool call. Failures are reported to the model as text rather
+    /// than aborting the agent, so it can correct itself.
+    pub async fn execute(&self, call: &ToolCall) -> String {
+        info!(
+            "Tool call: {}({})",
+            call.function.name, call.function.arguments
+        );
+
+        let result = match call.function.name.as_str() {
+            "read_file" => self.read_file(&call.function.arguments),
+            "list_dir" => self.list_dir(&call.function.arguments),
+            "grep" => self.grep(&call.function.arguments),
+            "run_command" => self.run_command(&call.function.arguments).await,
+            "http_request" => self.http_request(&call.function.arguments).await,
+            other => Err(anyhow!("Unknown tool: {}", other)),
+        };
+
+        match result {
+            Ok(output) => truncate(output),
+            Err(e) => {
+                warn!("Tool {} failed: {}", call.function.name, e);
+                format!("ERROR: {}", e)
+            }
+        }
+    }
+
+    /// Resolve a model-supplied path, refusing anything that escapes the workspace.
+    fn resolve(&self, relative: &str) -> Result<PathBuf> {
+        let relative = Path::new(relative.trim_start_matches('/'));
+
+        if relative
+            .components()
+            .any(|c| matches!(c, Component::ParentDir | Component::Prefix(_)))
+        {
+            return Err(anyhow!(
+                "Path {} is outside the workspace",
+                relative.display()
+            ));
+        }
+
+        let path = self.root.join(relative);
+        let canonical_root = self.root.canonicalize()?;
+        let canonical = path
+            .canonicalize()
+            .map_err(|e| anyhow!("{}: {}", relative.display(), e))?;
+
+        if !canonical.starts_with(&canonical_root) {
+            return Err(anyhow!(
+                "Path {} is outside the workspace",
+                relative.display()
+            ));
+        }
+
+        Ok(canonical)
+    }
+
+    fn read_file(&self, arguments: &str) -> Result<String> {
+        let args: PathArgs = serde_json::from_str(arguments)?;
+        let path = self.resolve(&args.path)?;
+        Ok(std::fs::read_to_string(path)?)
+    }
+
+    fn list_dir(&self, arguments: &str) -> Result<String> {
+        let args: PathArgs = serde_json::from_str(arguments)?;
+        let path = self.resolve(&args.path)?;
+
+        let mut entries = std::fs::read_dir(path)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if entry.path().is_dir() {
+                    format!("{}/", name)
+                } else {
+                    name
+                }
+            })
+            .collect::<Vec<_>>();
+        entries.sort();
+
+        Ok(entries.join("\n"))
+    }
+
+    fn grep(&self, arguments: &str) -> Result<String> {
+        let args: GrepArgs = serde_json::from_str(arguments)?;
+        let pattern = Regex::new(&args.pattern)?;
+        let start = self.resolve(&args.path)?;
+        let canonical_root = self.root.canonicalize()?;
+
+        let mut matches = Vec::new();
+        let walker = WalkDir::new(&start)
+            .into_iter()
+            .filter_entry(|e| !SKIPPED_DIRS.contains(&e.file_name().to_string_lossy().as_ref()));
+
+        for entry in walker
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+        {
+            let Ok(content) = std::fs::read_to_string(entry.path()) else {
+                continue; // binary or unreadable
+            };
+            let display = entry
+                .path()
+                .strip_prefix(&canonical_root)
+                .unwrap_or(entry.path());
+
+            for (number, line) in content.lines().enumerate() {
+                if pattern.is_match(line) {
+                    matches.push(format!(
+                        "{}:{}: {}",
+                        display.display(),
+                        number + 1,
+                        line.trim()
+                    ));
+                    if matches.len() >= MAX_GREP_MATCHES {
+                        matches.push("... (more matches omitted)".to_string());
+                        return Ok(matches.join("\n"));
+                    }
+                }
+            }
+        }
+
+        if matches.is_empty() {
+            Ok("No matches".to_string())
+        } else {
+            Ok(matches.join("\n"))
+        }
+    }
+
+    async fn run_command(&self, arguments: &str) -> Result<String> {
+        let args: CommandArgs = serde_json::from_str(arguments)?;
+        let timeout = Duration::from_secs(
+            args.timeout_secs
+                .unwrap_or(DEFAULT_COMMAND_TIMEOUT_SECS)
+                .min(MAX_COMMAND_TIMEOUT_SECS),
+        );
+
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(&args.command)
+            .current_dir(&self.root)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        // Own process group, so servers and installs it starts stop with it
+        #[cfg(unix)]
+        command.process_group(0);
+
+        let child = command.spawn()?;
+        let pid = child.id();
+        let output = tokio::time::timeout(timeout, child.wait_with_output()).await;
+        if let Some(pid) = pid {
+            kill_process_group(pid);
+        }
+        let output =
+            output.map_err(|_| anyhow!("Command timed out after {}s", timeout.as_secs()))??;
+
+        Ok(format!(
+            "exit code: {}\nstdout:\n{}\nstderr:\n{}",
+            output
+                .status
+                .code()
+                .map_or("killed".to_string(), |c| c.to_string()),
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        ))
+    }
+
+    async fn http_request(&self, arguments: &str) -> Result<String> {
+        let args: HttpArgs = serde_json::from_str(arguments)?;
+        let url = reqwest::Url::parse(&args.url)?;
+
+        if !matches!(
+            url.host_str(),
+            Some("localhost") | Some("127.0.0.1") | Some("[::1]")
+        ) {
+            return Err(anyhow!("Only requests to localhost are allowed"));
+        }
+
+        let method = reqwest::Method::from_bytes(args.method.to_uppercase().as_bytes())?;
+        let mut request = self.http.request(method, url);
+        if let Some(body) = args.body {
+            request = request
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body);
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+
+        Ok(format!("status: {}\nbody:\n{}", status, body))
+    }
+}
+
+#[async_trait]
+impl ToolSet for WorkspaceTools {
+    fn definitions(&self) -> Vec<ToolDefinition> {
+        WorkspaceTools::definitions(self)
+    }
+
+    async fn execute(&self, call: &ToolCall) -> String {
+        WorkspaceTools::execute(self, call).await
+    }
+}
+
+pub(crate) fn truncate(mut output: String) -> String {
+    if output.len() > MAX_OUTPUT_BYTES {
+        let mut cut = MAX_OUTPUT_BYTES;
+        while !output.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        output.truncate(cut);
+        output.push_str("\n... (output truncated)");
+    }
+    output
+}
+
+/// Stop every process in the group `pid` leads, e.g. what `npm` spawned.
+pub(crate) fn kill_process_group(pid: u32) {
+    #[cfg(unix)]
+    let _ = std::process::Command::new("kill")
+        .arg("-KILL")
+        .arg(format!("-{}", pid))
+        .stderr(Stdio::null())
+        .status();
+    #[cfg(not(unix))]
+    let _ = pid;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn timed_out_command_stops_its_children() {
+        let workspace = tempfile::tempdir().unwrap();
+        let tools = WorkspaceTools::new(workspace.path().to_path_buf()).unwrap();
+        let arguments =
+            json!({ "command": "sleep 30 & echo $! > child.pid; wait", "timeout_secs": 1 });
+
+        let started = Instant::now();
+        let error = tools.run_command(&arguments.to_string()).await.unwrap_err();
+        assert!(error.to_string().contains("timed out after 1s"));
+        assert!(started.elapsed() < Duration::from_secs(10));
+
+        let pid = std::fs::read_to_string(workspace.path().join("child.pid")).unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let alive = std::process::Command::new("kill")
+            .arg("-0")
+            .arg(pid.trim())
+            .stderr(Stdio::null())
+            .status()
+            .unwrap()
+            .success();
+        assert!(!alive, "background sleep survived the timeout");
+    }
+
+    #[tokio::test]
+    async fn command_reports_exit_code_and_output() {
+        let workspace = tempfile::tempdir().unwrap();
+        let tools = WorkspaceTools::new(workspace.path().to_path_buf()).unwrap();
+        let arguments = json!({ "command": "echo hi; exit 3", "timeout_secs": 100000 });
+
+        let output = tools.run_command(&arguments.to_string()).await.unwrap();
+        assert!(output.starts_with("exit code: 3\nstdout:\nhi\n"));
+    }
+}
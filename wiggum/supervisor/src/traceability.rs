@@ -0,0 +1,205 @@
+use crate::acceptance::AcceptanceResult;
+use crate::state::{Intent, Task};
+use crate::test_runner::{TestRun, TestStatus};
+use crate::verdict::{Finding, Severity};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CriterionStatus {
+    /// Every piece of evidence that ran passed
+    Passing,
+    /// At least one piece of evidence failed
+    Failing,
+    /// Evidence exists but none of it could run
+    NotRun,
+    /// No test references the criterion
+    Uncovered,
+}
+
+/// A test result that speaks for a criterion.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Evidence {
+    /// e.g. `acceptance AT-2` or `vitest server: adds a todo (AC-1)`
+    pub source: String,
+    pub passed: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CriterionTrace {
+    pub id: String,
+    pub text: String,
+    /// Tasks that claim to deliver the criterion
+    pub tasks: Vec<String>,
+    pub evidence: Vec<Evidence>,
+    pub status: CriterionStatus,
+}
+
+/// Which intent criteria have passing evidence (state/traceability.json),
+/// rebuilt on every verification pass and checked before the loop exits.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceabilityReport {
+    pub generated_at: DateTime<Utc>,
+    pub criteria: Vec<CriterionTrace>,
+}
+
+pub fn report_path(state_dir: &Path) -> PathBuf {
+    state_dir.join("traceability.json")
+}
+
+/// Criterion ids mentioned in free text, e.g. a test named `adds a todo [AC-1]`.
+pub fn referenced_criteria(text: &str) -> Vec<String> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    let pattern = PATTERN.get_or_init(|| Regex::new(r"(?i)\bAC-(\d+)\b").unwrap());
+
+    let mut ids: Vec<String> = pattern
+        .captures_iter(text)
+        .map(|captures| format!("AC-{}", &captures[1]))
+        .collect();
+    ids.dedup();
+    ids
+}
+
+impl TraceabilityReport {
+    /// Link each criterion of the intent to the tasks that reference it and
+    /// to the acceptance results and workspace test cases that cover it.
+    /// Acceptance tests reference criteria explicitly; workspace tests by
+    /// naming them (`AC-3`) in the test name.
+    pub fn build(
+        intent: &Intent,
+        tasks: &[Task],
+        acceptance: &[AcceptanceResult],
+        test_runs: &[TestRun],
+    ) -> Self {
+        let criteria = intent
+            .criteria
+            .iter()
+            .map(|criterion| {
+                let tasks = tasks
+                    .iter()
+                    .filter(|t| t.criteria.contains(&criterion.id))
+                    .map(|t| t.id.clone())
+                    .collect();
+
+                let mut evidence: Vec<Evidence> = acceptance
+                    .iter()
+                    .filter(|r| r.criteria.contains(&criterion.id))
+                    .map(|r| Evidence {
+                        source: format!("acceptance {}", r.test_id),
+                        passed: r.passed,
+                    })
+                    .collect();
+
+                for run in test_runs {
+                    for case in &run.cases {
+                        if !referenced_criteria(&case.name).contains(&criterion.id) {
+                            continue;
+                        }
+                        evidence.push(Evidence {
+                            source: format!("{} {}: {}", run.kind.name(), run.dir, case.name),
+                            passed: match case.status {
+                                TestStatus::Passed => Some(true),
+                                TestStatus::Failed => Some(false),
+                                TestStatus::Skipped => None,
+                            },
+                        });
+                    }
+                }
+
+                CriterionTrace {
+                    id: criterion.id.clone(),
+                    text: criterion.text.clone(),
+                    tasks,
+                    status: status(&evidence),
+                    evidence,
+                }
+            })
+            .collect();
+
+        Self {
+            generated_at: Utc::now(),
+            criteria,
+        }
+    }
+
+    pub fn load(state_dir: &Path) -> Result<Option<Self>> {
+        let path = report_path(state_dir);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(path)?;
+        Ok(Some(serde_json::from_str(&content)?))
+    }
+
+    pub fn save(&self, state_dir: &Path) -> Result<()> {
+        fs::create_dir_all(state_dir)?;
+        fs::write(report_path(state_dir), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Criteria without passing evidence.
+    pub fn unmet(&self) -> Vec<&CriterionTrace> {
+        self.criteria
+            .iter()
+            .filter(|c| c.status != CriterionStatus::Passing)
+            .collect()
+    }
+
+    /// One line per criterion with its status and evidence.
+    pub fn render(&self) -> String {
+        self.criteria
+            .iter()
+            .map(|c| {
+                let status = match c.status {
+                    CriterionStatus::Passing => "passing",
+                    CriterionStatus::Failing => "FAILING",
+                    CriterionStatus::NotRun => "not run",
+                    CriterionStatus::Uncovered => "UNCOVERED",
+                };
+                let evidence = if c.evidence.is_empty() {
+                    "no tests".to_string()
+                } else {
+                    c.evidence
+                        .iter()
+                        .map(|e| e.source.as_str())
+                        .collect::<Vec<_>>()
+                        .join("; ")
+                };
+                format!("{} {}: {} ({})", c.id, status, c.text, evidence)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn findings(&self) -> Vec<Finding> {
+        self.unmet()
+            .into_iter()
+            .map(|c| {
+                let message = match c.status {
+                    CriterionStatus::Failing => format!("Acceptance criterion {} fails its tests: {}", c.id, c.text),
+                    CriterionStatus::NotRun => format!("Acceptance criterion {} has tests but none ran: {}", c.id, c.text),
+                    _ => format!("Acceptance criterion {} has no tests; add one naming {} or covering it in the acceptance suite: {}", c.id, c.id, c.text),
+                };
+                Finding::new(Severity::Major, message).rule(format!("trace:{}", c.id))
+            })
+            .collect()
+    }
+}
+
+fn status(evidence: &[Evidence]) -> CriterionStatus {
+    if evidence.is_empty() {
+        CriterionStatus::Uncovered
+    } else if evidence.iter().any(|e| e.passed == Some(false)) {
+        CriterionStatus::Failing
+    } else if evidence.iter().any(|e| e.passed == Some(true)) {
+        CriterionStatus::Passing
+    } else {
+        CriterionStatus::NotRun
+    }
+}
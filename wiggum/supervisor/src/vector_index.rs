@@ -0,0 +1,224 @@
+use crate::llm::LlmClient;
+use crate::repo_map::RepoMap;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+
+const CHUNK_LINES: usize = 40;
+/// Lines shared by neighbouring chunks so code at a boundary is not split
+/// from its context.
+const CHUNK_OVERLAP: usize = 8;
+/// Chunks sent per embeddings request.
+const EMBED_BATCH: usize = 16;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Chunk {
+    pub start_line: usize,
+    pub end_line: usize,
+    pub text: String,
+    vector: Vec<f32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct IndexedFile {
+    hash: String,
+    chunks: Vec<Chunk>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub path: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub text: String,
+    pub score: f32,
+}
+
+/// Embeddings of workspace source chunks (state/vector_index.json).
+///
+/// Files are keyed by content hash, so `update` only re-embeds what changed
+/// since the last tick. Switching embedding model starts from scratch.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct VectorIndex {
+    model: String,
+    files: BTreeMap<String, IndexedFile>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl VectorIndex {
+    pub fn load(state_dir: &Path, model: &str) -> Result<Self> {
+        let path = state_dir.join("vector_index.json");
+
+        let mut index: VectorIndex = if path.exists() {
+            let content = fs::read_to_string(&path)?;
+            serde_json::from_str(&content)?
+        } else {
+            VectorIndex::default()
+        };
+
+        if index.model != model {
+            if !index.model.is_empty() {
+                info!(
+                    "Embedding model changed from {} to {}, rebuilding index",
+                    index.model, model
+                );
+            }
+            index.files.clear();
+            index.model = model.to_string();
+        }
+
+        index.path = path;
+        Ok(index)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Bring the index in line with the workspace: embed new and changed
+    /// source files and forget deleted ones. Returns how many were embedded.
+    pub async fn update(&mut self, llm: &LlmClient, root: &Path) -> Result<usize> {
+        let map = RepoMap::build(root)?;
+        let sources: Vec<&str> = map
+            .files()
+            .iter()
+            .filter(|f| f.lines > 0 && f.is_source())
+            .map(|f| f.path.as_str())
+            .collect();
+
+        let before = self.files.len();
+        self.files
+            .retain(|path, _| sources.contains(&path.as_str()));
+        if self.files.len() < before {
+            debug!(
+                "Dropped {} deleted files from the vector index",
+                before - self.files.len()
+            );
+        }
+
+        let mut embedded = 0;
+        for path in sources {
+            let Ok(content) = fs::read_to_string(root.join(path)) else {
+                continue;
+            };
+            let hash = content_hash(&content);
+            if self.files.get(path).is_some_and(|f| f.hash == hash) {
+                continue;
+            }
+
+            let chunks = self.embed_file(llm, path, &content).await?;
+            self.files
+                .insert(path.to_string(), IndexedFile { hash, chunks });
+            embedded += 1;
+        }
+
+        if embedded > 0 {
+            info!("Embedded {} changed files into the vector index", embedded);
+            self.save()?;
+        }
+        Ok(embedded)
+    }
+
+    /// The `limit` chunks most similar to `query`, best first.
+    pub async fn search(
+        &self,
+        llm: &LlmClient,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<SearchHit>> {
+        let Some(query_vector) = llm.embed(&self.model, &[query.to_string()]).await?.pop() else {
+            return Ok(Vec::new());
+        };
+
+        let mut hits: Vec<SearchHit> = self
+            .files
+            .iter()
+            .flat_map(|(path, file)| file.chunks.iter().map(move |chunk| (path, chunk)))
+            .map(|(path, chunk)| SearchHit {
+                path: path.clone(),
+                start_line: chunk.start_line,
+                end_line: chunk.end_line,
+                text: chunk.text.clone(),
+                score: cosine_similarity(&query_vector, &chunk.vector),
+            })
+            .collect();
+
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        hits.truncate(limit);
+        Ok(hits)
+    }
+
+    async fn embed_file(&self, llm: &LlmClient, path: &str, content: &str) -> Result<Vec<Chunk>> {
+        let mut chunks = split_into_chunks(content);
+
+        for batch in chunks.chunks_mut(EMBED_BATCH) {
+            // The path tells the model what the snippet belongs to
+            let input: Vec<String> = batch
+                .iter()
+                .map(|c| format!("{}\n{}", path, c.text))
+                .collect();
+            let vectors = llm.embed(&self.model, &input).await?;
+            for (chunk, vector) in batch.iter_mut().zip(vectors) {
+                chunk.vector = vector;
+            }
+        }
+
+        Ok(chunks)
+    }
+}
+
+fn content_hash(content: &str) -> String {
+    Sha256::digest(content.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn split_into_chunks(content: &str) -> Vec<Chunk> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+
+    while start < lines.len() {
+        let end = (start + CHUNK_LINES).min(lines.len());
+        let text = lines[start..end].join("\n");
+        if !text.trim().is_empty() {
+            chunks.push(Chunk {
+                start_line: start + 1,
+                end_line: end,
+                text,
+                vector: Vec::new(),
+            });
+        }
+        if end == lines.len() {
+            break;
+        }
+        start = end - CHUNK_OVERLAP;
+    }
+
+    chunks
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
@@ -0,0 +1,238 @@
+use crate::agents::AgentResult;
+use crate::llm::{Conversation, LlmClient};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::warn;
+
+/// How many times a malformed verdict is sent back to the model for repair.
+pub const MAX_REPAIR_ATTEMPTS: usize = 2;
+
+/// Output contract appended to every gate agent's system prompt.
+pub const VERDICT_INSTRUCTIONS: &str = "Respond with a single JSON object and nothing else:
+{
+  \"status\": \"pass\" | \"fail\",
+  \"summary\": \"one or two sentences\",
+  \"findings\": [
+    { \"severity\": \"critical\" | \"major\" | \"minor\", \"message\": \"what is wrong and how to fix it\", \"file\": \"path or null\", \"line\": number or null, \"rule\": \"check name or null\" }
+  ]
+}
+A \"fail\" verdict must list at least one finding.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerdictStatus {
+    Pass,
+    Fail,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Minor,
+    Major,
+    Critical,
+}
+
+/// One concrete problem a gate found.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Finding {
+    pub severity: Severity,
+    pub message: String,
+    #[serde(default)]
+    pub file: Option<String>,
+    #[serde(default)]
+    pub line: Option<u32>,
+    #[serde(default)]
+    pub rule: Option<String>,
+}
+
+impl Finding {
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            message: message.into(),
+            file: None,
+            line: None,
+            rule: None,
+        }
+    }
+
+    pub fn at(mut self, file: impl Into<String>, line: Option<u32>) -> Self {
+        self.file = Some(file.into());
+        self.line = line;
+        self
+    }
+
+    pub fn rule(mut self, rule: impl Into<String>) -> Self {
+        self.rule = Some(rule.into());
+        self
+    }
+}
+
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{:?}]", self.severity)?;
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, " {}:{}", file, line)?,
+            (Some(file), None) => write!(f, " {}", file)?,
+            _ => {}
+        }
+        if let Some(rule) = &self.rule {
+            write!(f, " ({})", rule)?;
+        }
+        write!(f, " {}", self.message)
+    }
+}
+
+/// Structured answer of a verification gate.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Verdict {
+    pub status: VerdictStatus,
+    pub summary: String,
+    #[serde(default)]
+    pub findings: Vec<Finding>,
+}
+
+impl Verdict {
+    /// JSON schema sent as `response_format` to servers that support it.
+    pub fn json_schema() -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "status": { "type": "string", "enum": ["pass", "fail"] },
+                "summary": { "type": "string" },
+                "findings": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "severity": { "type": "string", "enum": ["critical", "major", "minor"] },
+                            "message": { "type": "string" },
+                            "file": { "type": ["string", "null"] },
+                            "line": { "type": ["integer", "null"] },
+                            "rule": { "type": ["string", "null"] }
+                        },
+                        "required": ["severity", "message", "file", "line", "rule"],
+                        "additionalProperties": false
+                    }
+                }
+            },
+            "required": ["status", "summary", "findings"],
+            "additionalProperties": false
+        })
+    }
+
+    pub fn response_format() -> serde_json::Value {
+        json!({
+            "type": "json_schema",
+            "json_schema": {
+                "name": "verdict",
+                "strict": true,
+                "schema": Self::json_schema()
+            }
+        })
+    }
+
+    /// Parse a model reply, tolerating code fences and surrounding prose,
+    /// and check the rules serde cannot express.
+    pub fn parse(reply: &str) -> Result<Self> {
+        let start = reply
+            .find('{')
+            .ok_or_else(|| anyhow!("no JSON object in reply"))?;
+        let end = reply
+            .rfind('}')
+            .ok_or_else(|| anyhow!("no JSON object in reply"))?;
+        if end < start {
+            return Err(anyhow!("no JSON object in reply"));
+        }
+
+        let verdict: Verdict = serde_json::from_str(&reply[start..=end])?;
+
+        if verdict.summary.trim().is_empty() {
+            return Err(anyhow!("summary must not be empty"));
+        }
+        if verdict.status == VerdictStatus::Fail && verdict.findings.is_empty() {
+            return Err(anyhow!("a fail verdict must list at least one finding"));
+        }
+
+        Ok(verdict)
+    }
+
+    /// Parse `reply`, the last assistant message of `conversation`. If it is
+    /// malformed, tell the model what was wrong and ask again, up to
+    /// `MAX_REPAIR_ATTEMPTS` times.
+    pub async fn parse_or_repair(
+        llm: &LlmClient,
+        conversation: &mut Conversation,
+        reply: &str,
+    ) -> Result<Self> {
+        let mut error = match Self::parse(reply) {
+            Ok(verdict) => return Ok(verdict),
+            Err(e) => e,
+        };
+
+        for attempt in 1..=MAX_REPAIR_ATTEMPTS {
+            warn!(
+                "Malformed verdict ({}), repair attempt {}/{}",
+                error, attempt, MAX_REPAIR_ATTEMPTS
+            );
+
+            conversation.push_user(&format!(
+                "Your reply could not be used: {}.\n\n{}",
+                error, VERDICT_INSTRUCTIONS
+            ));
+            let reply = llm
+                .converse_structured(conversation, llm.model(), Self::response_format())
+                .await?;
+
+            match Self::parse(reply.content.as_deref().unwrap_or_default()) {
+                Ok(verdict) => return Ok(verdict),
+                Err(e) => error = e,
+            }
+        }
+
+        Err(anyhow!(
+            "No valid verdict after {} repair attempts: {}",
+            MAX_REPAIR_ATTEMPTS,
+            error
+        ))
+    }
+
+    /// Add findings the gate established itself, such as failing tests.
+    /// Any of them fails the verdict whatever the model concluded.
+    pub fn with_findings(mut self, findings: Vec<Finding>) -> Self {
+        if findings.is_empty() {
+            return self;
+        }
+
+        if self.passed() {
+            self.status = VerdictStatus::Fail;
+            self.summary = format!(
+                "{} Overruled: {} automated check(s) failed.",
+                self.summary,
+                findings.len()
+            );
+        }
+        self.findings.extend(findings);
+        self
+    }
+
+    pub fn passed(&self) -> bool {
+        self.status == VerdictStatus::Pass
+    }
+}
+
+impl From<Verdict> for AgentResult {
+    fn from(verdict: Verdict) -> Self {
+        if verdict.passed() {
+            return AgentResult::Success;
+        }
+
+        let mut reason = verdict.summary;
+        for finding in &verdict.findings {
+            reason.push_str(&format!("\n- {}", finding));
+        }
+        AgentResult::Failure(reason)
+    }
+}